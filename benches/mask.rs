@@ -0,0 +1,55 @@
+//! Compares client frame encoding against a byte-wise masking loop.
+//!
+//! Run with `cargo bench --bench mask`, and with `--features safe-encode` to compare the safe encoder.
+
+use std::{hint::black_box, time::Instant};
+use web_socket::Frame;
+
+const MASK: [u8; 4] = [0x37, 0xfa, 0x21, 0x3d];
+const ITERATIONS: u32 = 200;
+
+fn bytewise(data: &[u8], buf: &mut Vec<u8>) {
+    buf.clear();
+    buf.extend_from_slice(&[0x82, 0xff, 0, 0, 0, 0, 0, 0x10, 0, 0]);
+    buf.extend_from_slice(&MASK);
+    buf.extend(data.iter().enumerate().map(|(i, byte)| byte ^ MASK[i & 3]));
+}
+
+fn bench(name: &str, bytes: usize, mut f: impl FnMut()) {
+    f();
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let elapsed = start.elapsed() / ITERATIONS;
+    let throughput = bytes as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64();
+    println!("{name:<10} {elapsed:>12?}/iter {throughput:>10.1} MiB/s");
+}
+
+fn main() {
+    let data = vec![0x5a; 1024 * 1024];
+    let mut buf = Vec::with_capacity(data.len() + 14);
+
+    bench("bytewise", data.len(), || {
+        bytewise(black_box(&data), &mut buf);
+        black_box(&buf);
+    });
+    bench("masked", data.len(), || {
+        buf.clear();
+        Frame::from(black_box(&data[..])).encode_with_into(MASK, &mut buf);
+        black_box(&buf);
+    });
+    bench("unmasked", data.len(), || {
+        buf.clear();
+        Frame::from(black_box(&data[..])).encode_without_mask_into(&mut buf);
+        black_box(&buf);
+    });
+    // where the header dominates
+    bench("small", 16 * 4096, || {
+        buf.clear();
+        for _ in 0..4096 {
+            Frame::from(black_box(&data[..16])).encode_with_into(MASK, &mut buf);
+        }
+        black_box(&buf);
+    });
+}
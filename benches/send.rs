@@ -0,0 +1,131 @@
+//! Compares sending server frames with vectored writes, against copying the payload into the write buffer.
+//! Allocations are counted too, the write buffer is reused so a send shouldn't allocate once it has grown.
+//!
+//! Run with `cargo bench --bench send`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    io::{IoSlice, Result},
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering::Relaxed},
+    task::{Context, Poll},
+    time::Instant,
+};
+use tokio::io::AsyncWrite;
+use web_socket::WebSocket;
+
+const ITERATIONS: u32 = 200;
+
+/// Counts the allocations of the process.
+struct Counter;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for Counter {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counter = Counter;
+
+/// Discards everything, `vectored` selects the send path of the server.
+struct Discard {
+    vectored: bool,
+}
+
+impl AsyncWrite for Discard {
+    fn poll_write(self: Pin<&mut Self>, _: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize>> {
+        Poll::Ready(Ok(black_box(buf).len()))
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<Result<usize>> {
+        Poll::Ready(Ok(black_box(bufs).iter().map(|buf| buf.len()).sum()))
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.vectored
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+async fn bench(name: &str, mut ws: WebSocket<Discard>, data: &[u8]) -> Result<()> {
+    ws.send(data).await?;
+    ws.send_ping(&data[..data.len().min(125)]).await?;
+    let allocations = ALLOCATIONS.load(Relaxed);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        ws.send(black_box(data)).await?;
+    }
+    let elapsed = start.elapsed() / ITERATIONS;
+    for _ in 0..ITERATIONS {
+        ws.send_ping(black_box(&data[..data.len().min(125)]))
+            .await?;
+    }
+    let allocations = (ALLOCATIONS.load(Relaxed) - allocations) as f64 / (2 * ITERATIONS) as f64;
+    let throughput = data.len() as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64();
+    println!(
+        "{name:<12} {elapsed:>12?}/iter {throughput:>10.1} MiB/s {allocations:>6.2} allocs/iter"
+    );
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+    runtime.block_on(async {
+        let data = vec![0x5a; 1024 * 1024];
+        let server = |vectored| WebSocket::server(Discard { vectored });
+        bench("vectored", server(true), &data).await?;
+        bench("copy", server(false), &data).await?;
+        bench(
+            "client",
+            WebSocket::client(Discard { vectored: false }),
+            &data,
+        )
+        .await?;
+        // where the header dominates
+        bench("small", server(true), &data[..16]).await?;
+        bench("small copy", server(false), &data[..16]).await?;
+        bench(
+            "small client",
+            WebSocket::client(Discard { vectored: false }),
+            &data[..16],
+        )
+        .await?;
+
+        let mut allocations = 0;
+        for _ in 0..ITERATIONS {
+            let mut ws = server(true);
+            let start = ALLOCATIONS.load(Relaxed);
+            ws.send_close((web_socket::CloseCode::Normal, black_box("going away")))
+                .await?;
+            allocations += ALLOCATIONS.load(Relaxed) - start;
+        }
+        let allocations = allocations as f64 / ITERATIONS as f64;
+        println!("{:<12} {allocations:>52.2} allocs/iter", "close");
+        Ok(())
+    })
+}
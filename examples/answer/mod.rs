@@ -21,8 +21,8 @@ where
             },
             Event::Ping(data) => ws.send_pong(data).await?,
             Event::Pong(_) => {}
-            Event::Error(_) => return ws.close(CloseCode::ProtocolError).await,
-            Event::Close { .. } => return ws.close(()).await,
+            Event::Error(err) => return ws.close(err.close_code()).await,
+            Event::Close { .. } | Event::Closed => return ws.close(()).await,
         }
     }
 }
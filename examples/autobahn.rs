@@ -26,6 +26,6 @@ async fn get_case_count() -> Option<u32> {
 }
 
 async fn update_reports() -> Result<()> {
-    let ws = connect(ADDR, &format!("/updateReports?{AGENT}")).await?;
+    let mut ws = connect(ADDR, &format!("/updateReports?{AGENT}")).await?;
     ws.close(()).await
 }
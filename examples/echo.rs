@@ -2,12 +2,8 @@ mod answer;
 mod utils;
 
 use tokio::{io::*, net::TcpListener};
-use utils::Http;
-use web_socket::*;
 
-use crate::utils::handshake;
-
-#[allow(dead_code)]
+#[allow(dead_code, clippy::unit_arg)]
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
     let Some(mut addr) = std::env::args().nth(1)  else {
@@ -21,28 +17,13 @@ async fn main() -> Result<()> {
 
     loop {
         let (stream, addr) = listener.accept().await?;
-        let mut stream = BufReader::new(stream);
-        let http = Http::parse(&mut stream).await?;
-
-        let Some(key) = get_sec_key(&http) else {
-            panic!("[{addr}] error: expected websocket upgrade request");
-        };
-
-        stream
-            .write_all(handshake::response(key, [("x-agent", "web-socket")]).as_bytes())
-            .await?;
-
-        tokio::spawn(async {
-            let _ = answer::echo(WebSocket::server(stream)).await;
+        tokio::spawn(async move {
+            match utils::accept(stream).await {
+                Ok(ws) => {
+                    let _ = answer::echo(ws).await;
+                }
+                Err(error) => eprintln!("[{addr}] error: {error}"),
+            }
         });
     }
 }
-
-fn get_sec_key(http: &Http) -> Option<&String> {
-    if !http.get("connection")?.eq_ignore_ascii_case("upgrade")
-        || !http.get("upgrade")?.eq_ignore_ascii_case("websocket")
-    {
-        return None;
-    }
-    http.get("sec-websocket-key")
-}
@@ -22,10 +22,9 @@ where
                 assert!(matches!(ty, DataType::Complete(MessageType::Text)));
                 assert_eq!(&*data, b"Copy Cat!");
             }
-            Event::Ping(data) => ws.send_pong(data).await?,
-            Event::Pong(..) => {}
-            Event::Error(..) => return ws.close(CloseCode::ProtocolError).await,
-            Event::Close { .. } => return ws.close(()).await,
+            Event::Ping(..) | Event::Pong(..) => {}
+            Event::Error(err) => return ws.close(err.close_code()).await,
+            Event::Close { .. } | Event::Closed => return ws.close(()).await,
         }
     }
     ws.close("bye!").await
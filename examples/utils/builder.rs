@@ -0,0 +1,319 @@
+use super::{handshake, Http, MAX_HEAD_LEN};
+use socket2::{SockRef, TcpKeepalive};
+use std::{io::Result, time::Duration};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    net::{lookup_host, TcpSocket, TcpStream, ToSocketAddrs},
+};
+use web_socket::WebSocket;
+#[cfg(feature = "deflate")]
+use web_socket::{deflate::DeflateConfig, Role};
+
+/// Configuration of a client connection.
+///
+/// The defaults are the same as those of [super::connect_url] and [WebSocket::client].
+///
+/// ### Example
+///
+/// ```no_run
+/// use crate::utils::ClientBuilder;
+/// use std::time::Duration;
+///
+/// # async {
+/// let (ws, res) = ClientBuilder::new()
+///     .origin("https://example.com")
+///     .protocols(["graphql-ws"])
+///     .keepalive(Duration::from_secs(30))
+///     .connect("ws://example.com/graphql")
+///     .await?;
+/// # std::io::Result::Ok(())
+/// # };
+/// ```
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    headers: Vec<(String, String)>,
+    protocols: Vec<String>,
+    max_head_len: usize,
+    max_payload_len: Option<usize>,
+    max_message_len: Option<usize>,
+    keepalive: Option<Duration>,
+    nodelay: bool,
+    tcp_keepalive: Option<TcpKeepalive>,
+    proxy: Option<Proxy>,
+}
+
+#[derive(Debug, Clone)]
+struct Proxy {
+    addr: String,
+    /// Value of the `Proxy-Authorization` header.
+    authorization: Option<String>,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self {
+            headers: Vec::new(),
+            protocols: Vec::new(),
+            max_head_len: MAX_HEAD_LEN,
+            max_payload_len: None,
+            max_message_len: None,
+            keepalive: None,
+            nodelay: true,
+            tcp_keepalive: None,
+            proxy: None,
+        }
+    }
+}
+
+impl ClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a header to the handshake request.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Send an `Origin` header, for servers that check the origin.
+    pub fn origin(self, origin: impl Into<String>) -> Self {
+        self.header("Origin", origin)
+    }
+
+    /// Send an `Authorization: Basic` header, with the base64-encoded `user:password`.
+    pub fn basic_auth(self, user: &str, password: &str) -> Self {
+        let credentials = handshake::base64_encode(format!("{user}:{password}"));
+        self.header("Authorization", format!("Basic {credentials}"))
+    }
+
+    /// Send an `Authorization: Bearer` header.
+    pub fn bearer_auth(self, token: &str) -> Self {
+        self.header("Authorization", format!("Bearer {token}"))
+    }
+
+    /// Subprotocols to offer, in order of preference.
+    ///
+    /// The handshake fails if the server selects one that wasn't offered.
+    pub fn protocols(mut self, protocols: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.protocols = protocols.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Maximum length of the handshake response head, see [Http::parse_with_limit].
+    ///
+    /// Default: [MAX_HEAD_LEN]
+    pub fn max_head_len(mut self, len: usize) -> Self {
+        self.max_head_len = len;
+        self
+    }
+
+    /// See [WebSocket::max_payload_len]
+    pub fn max_payload_len(mut self, len: usize) -> Self {
+        self.max_payload_len = Some(len);
+        self
+    }
+
+    /// See [WebSocket::max_message_len]
+    pub fn max_message_len(mut self, len: usize) -> Self {
+        self.max_message_len = Some(len);
+        self
+    }
+
+    /// See [WebSocket::keepalive]
+    pub fn keepalive(mut self, interval: Duration) -> Self {
+        self.keepalive = Some(interval);
+        self
+    }
+
+    /// Set `TCP_NODELAY` on the connection, so small frames aren't delayed by Nagle's algorithm.
+    ///
+    /// Default: `true`
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = nodelay;
+        self
+    }
+
+    /// Set `SO_KEEPALIVE` on the connection, with `time` of idleness before the first probe and
+    /// `interval` between the probes. `None` keeps the value of the operating system.
+    ///
+    /// The interval is ignored on platforms where it can't be set, e.g. OpenBSD.
+    /// Unlike [ClientBuilder::keepalive], dead connections are detected without sending websocket frames.
+    ///
+    /// Default: disabled
+    pub fn tcp_keepalive(mut self, time: Option<Duration>, interval: Option<Duration>) -> Self {
+        let mut keepalive = TcpKeepalive::new();
+        if let Some(time) = time {
+            keepalive = keepalive.with_time(time);
+        }
+        // `interval` is unused on the platforms where it can't be set.
+        #[allow(unused_variables)]
+        if let Some(interval) = interval {
+            #[cfg(any(
+                target_os = "android",
+                target_os = "freebsd",
+                target_os = "ios",
+                target_os = "linux",
+                target_os = "macos",
+                target_os = "netbsd",
+                target_os = "windows",
+            ))]
+            {
+                keepalive = keepalive.with_interval(interval);
+            }
+        }
+        self.tcp_keepalive = Some(keepalive);
+        self
+    }
+
+    /// Connect through an http proxy at `addr`, which tunnels the connection with a `CONNECT` request.
+    ///
+    /// `auth` is the `user` and `password` for the proxy, sent in a `Proxy-Authorization: Basic` header.
+    pub fn proxy(mut self, addr: impl Into<String>, auth: Option<(&str, &str)>) -> Self {
+        let authorization = auth.map(|(user, password)| {
+            let credentials = handshake::base64_encode(format!("{user}:{password}"));
+            format!("Basic {credentials}")
+        });
+        self.proxy = Some(Proxy {
+            addr: addr.into(),
+            authorization,
+        });
+        self
+    }
+
+    /// Opens a tcp connection to `host:port`, through the proxy if one is set.
+    ///
+    /// Useful to establish a TLS stream over it, before calling [ClientBuilder::client].
+    pub async fn open(&self, host: &str, port: u16) -> Result<TcpStream> {
+        let Some(proxy) = &self.proxy else {
+            return self.connect_tcp((host, port)).await;
+        };
+        let authority = match host.contains(':') {
+            true => format!("[{host}]:{port}"),
+            false => format!("{host}:{port}"),
+        };
+        let mut req = format!("CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\n");
+        if let Some(authorization) = &proxy.authorization {
+            req.push_str(&format!("Proxy-Authorization: {authorization}\r\n"));
+        }
+        req.push_str("\r\n");
+
+        let mut stream = BufReader::new(self.connect_tcp(proxy.addr.as_str()).await?);
+        stream.write_all(req.as_bytes()).await?;
+        let http = Http::parse_with_limit(&mut stream, self.max_head_len).await?;
+        if http.prefix().split_whitespace().nth(1) != Some("200") {
+            io_err!(ConnectionRefused, format!("proxy: {}", http.prefix()));
+        }
+        // The proxy doesn't send anything through the tunnel before the client does.
+        if !stream.buffer().is_empty() {
+            io_err!(InvalidData, "unexpected data from proxy");
+        }
+        Ok(stream.into_inner())
+    }
+
+    /// Tries every address `addr` resolves to, with the socket options of the builder.
+    async fn connect_tcp(&self, addr: impl ToSocketAddrs) -> Result<TcpStream> {
+        let mut err = None;
+        for addr in lookup_host(addr).await? {
+            let socket = match addr.is_ipv4() {
+                true => TcpSocket::new_v4()?,
+                false => TcpSocket::new_v6()?,
+            };
+            socket.set_nodelay(self.nodelay)?;
+            if let Some(keepalive) = &self.tcp_keepalive {
+                SockRef::from(&socket).set_tcp_keepalive(keepalive)?;
+            }
+            match socket.connect(addr).await {
+                Ok(stream) => return Ok(stream),
+                Err(e) => err = Some(e),
+            }
+        }
+        match err {
+            Some(err) => Err(err),
+            None => io_err!(InvalidInput, "could not resolve to any address"),
+        }
+    }
+
+    /// Connects to a `ws://` URL.
+    ///
+    /// `wss://` URLs are rejected, connect a TLS stream and use [ClientBuilder::client] instead.
+    /// The addresses of the connection are those of the stream, e.g. `ws.stream.get_ref().peer_addr()`.
+    pub async fn connect(&self, url: &str) -> Result<(WebSocket<BufReader<TcpStream>>, Http)> {
+        let Some(url) = handshake::Url::parse(url) else {
+            io_err!(InvalidInput, "invalid websocket url");
+        };
+        if url.secure {
+            io_err!(Unsupported, "`wss://` requires a TLS stream");
+        }
+        let stream = self.open(url.host, url.port).await?;
+        self.client(stream, &url.authority(), url.path).await
+    }
+
+    /// Performs the client handshake over an already connected `stream`, e.g. a TLS stream.
+    ///
+    /// Returns the server's response, e.g. to read the selected `sec-websocket-protocol` or a `set-cookie` header.
+    pub async fn client<IO>(
+        &self,
+        stream: IO,
+        host: &str,
+        path: &str,
+    ) -> Result<(WebSocket<BufReader<IO>>, Http)>
+    where
+        IO: Unpin + AsyncRead + AsyncWrite,
+    {
+        let mut stream = BufReader::new(stream);
+
+        #[cfg(feature = "deflate")]
+        let extensions = Some(("Sec-WebSocket-Extensions", "permessage-deflate"));
+        #[cfg(not(feature = "deflate"))]
+        let extensions = None::<(&str, &str)>;
+
+        let offer = self.protocols.join(", ");
+        let offer = (!offer.is_empty()).then_some(("Sec-WebSocket-Protocol", offer.as_str()));
+
+        let headers = extensions.into_iter().chain(offer).chain(
+            self.headers
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str())),
+        );
+        let (req, sec_key) = handshake::request(host, path, headers);
+        stream.write_all(req.as_bytes()).await?;
+
+        let http = Http::parse_with_limit(&mut stream, self.max_head_len).await?;
+
+        if !http
+            .prefix()
+            .starts_with("HTTP/1.1 101 Switching Protocols")
+        {
+            io_err!(InvalidData, "expected upgrade connection");
+        }
+        let Some(accept_key) = http.get("sec-websocket-accept") else {
+            io_err!(InvalidData, "expected `sec-websocket-accept` header");
+        };
+        if !handshake::verify_accept_key(&sec_key, accept_key) {
+            io_err!(InvalidData, "accept key mismatch");
+        }
+        if let Some(protocol) = http.get("sec-websocket-protocol") {
+            if !self.protocols.contains(protocol) {
+                io_err!(InvalidData, "unexpected `sec-websocket-protocol`");
+            }
+        }
+
+        let mut ws = WebSocket::client(stream);
+        #[cfg(feature = "deflate")]
+        if let Some(extensions) = http.get("sec-websocket-extensions") {
+            let Some(config) = DeflateConfig::negotiate(extensions, Role::Client) else {
+                io_err!(InvalidData, "unsupported websocket extension");
+            };
+            ws.enable_deflate(config);
+        }
+        if let Some(len) = self.max_payload_len {
+            ws.max_payload_len = len;
+        }
+        if let Some(len) = self.max_message_len {
+            ws.max_message_len = len;
+        }
+        ws.keepalive = self.keepalive;
+        Ok((ws, http))
+    }
+}
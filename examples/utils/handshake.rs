@@ -55,6 +55,94 @@ pub fn accept_key_from(sec_ws_key: impl AsRef<[u8]>) -> String {
     base64_encode(sha1.finalize())
 }
 
+/// Verify the server's `Sec-WebSocket-Accept` value against the `Sec-WebSocket-Key` we sent.
+///
+/// The comparison is done in constant time.
+pub fn verify_accept_key(sec_ws_key: impl AsRef<[u8]>, accept_key: impl AsRef<[u8]>) -> bool {
+    let expected = accept_key_from(sec_ws_key);
+    let (expected, accept_key) = (expected.as_bytes(), accept_key.as_ref());
+    if expected.len() != accept_key.len() {
+        return false;
+    }
+    expected
+        .iter()
+        .zip(accept_key)
+        .fold(0, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+/// Returns `true` if the headers are a websocket upgrade request, with a valid `Sec-WebSocket-Key`.
+///
+/// `header` looks up the value of a header by its lowercase name, so it can be used with any http stack.
+/// The request method (`GET`) isn't checked.
+///
+/// ### Example
+///
+/// ```rust
+/// use crate::utils::handshake::is_upgrade;
+///
+/// let is_upgrade = is_upgrade(|name| match name {
+///     "upgrade" => Some("websocket"),
+///     "connection" => Some("keep-alive, Upgrade"),
+///     "sec-websocket-version" => Some("13"),
+///     "sec-websocket-key" => Some("dGhlIHNhbXBsZSBub25jZQ=="),
+///     _ => None,
+/// });
+/// assert!(is_upgrade);
+/// ```
+pub fn is_upgrade<'a>(header: impl Fn(&str) -> Option<&'a str>) -> bool {
+    let has_token = |name: &str, token: &str| match header(name) {
+        Some(value) => value
+            .split(',')
+            .any(|v| v.trim().eq_ignore_ascii_case(token)),
+        None => false,
+    };
+    has_token("upgrade", "websocket")
+        && has_token("connection", "upgrade")
+        && header("sec-websocket-version").map(str::trim) == Some("13")
+        && header("sec-websocket-key").is_some_and(|key| is_sec_key(key.trim()))
+}
+
+/// Validate a raw handshake request head, returns its `Sec-WebSocket-Key`.
+///
+/// Returns `None` if it isn't a `GET` request, or not a websocket upgrade request. See [is_upgrade]
+///
+/// ### Example
+///
+/// ```rust
+/// use crate::utils::handshake::{validate_request, accept_key_from};
+///
+/// let req = "GET /chat HTTP/1.1\r\nHost: example.com\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n";
+/// let key = validate_request(req).unwrap();
+/// assert_eq!(accept_key_from(key), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+/// ```
+pub fn validate_request(raw: &str) -> Option<&str> {
+    let mut lines = raw.lines();
+    if !lines.next()?.starts_with("GET ") {
+        return None;
+    }
+    let headers: Vec<(&str, &str)> = lines
+        .take_while(|line| !line.is_empty())
+        .map(|line| line.split_once(':').map(|(k, v)| (k.trim(), v.trim())))
+        .collect::<Option<_>>()?;
+
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| *value)
+    };
+    match is_upgrade(header) {
+        true => header("sec-websocket-key"),
+        false => None,
+    }
+}
+
+/// The key is a base64-encoded value that, when decoded, is 16 bytes in length.
+fn is_sec_key(key: &str) -> bool {
+    base64::Engine::decode(&base64::prelude::BASE64_STANDARD, key).is_ok_and(|key| key.len() == 16)
+}
+
 /// ## Server handshake response
 ///
 /// When the server receives the handshake request,
@@ -96,9 +184,30 @@ pub fn response(
     sec_ws_key: impl AsRef<[u8]>,
     headers: impl IntoIterator<Item = impl Header>,
 ) -> String {
-    let key = accept_key_from(sec_ws_key);
-    let headers: String = headers.into_iter().map(|f| Header::fmt(&f)).collect();
-    format!("HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {key}\r\n{headers}\r\n")
+    let headers: String = response_headers(sec_ws_key)
+        .iter()
+        .map(Header::fmt)
+        .chain(headers.into_iter().map(|f| Header::fmt(&f)))
+        .collect();
+    format!("HTTP/1.1 101 Switching Protocols\r\n{headers}\r\n")
+}
+
+/// Headers of the `101 Switching Protocols` response, for http stacks that build the response themselves.
+///
+/// ### Example
+///
+/// ```rust,ignore
+/// let mut res = http::Response::builder().status(http::StatusCode::SWITCHING_PROTOCOLS);
+/// for (name, value) in response_headers(sec_ws_key) {
+///     res = res.header(name, value);
+/// }
+/// ```
+pub fn response_headers(sec_ws_key: impl AsRef<[u8]>) -> [(&'static str, String); 3] {
+    [
+        ("Upgrade", "websocket".into()),
+        ("Connection", "Upgrade".into()),
+        ("Sec-WebSocket-Accept", accept_key_from(sec_ws_key)),
+    ]
 }
 
 /// Create websocket handshake request
@@ -129,9 +238,127 @@ pub fn request(
 ) -> (String, String) {
     let host = host.as_ref();
     let path = path.as_ref().trim_start_matches('/');
-    let sec_key = base64_encode(42_u128.to_ne_bytes());
-    let headers: String = headers.into_iter().map(|f| Header::fmt(&f)).collect();
-    (format!("GET /{path} HTTP/1.1\r\nHost: {host}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Version: 13\r\nSec-WebSocket-Key: {sec_key}\r\n{headers}\r\n"), sec_key)
+    let (sec_key, upgrade) = request_headers();
+    let headers: String = upgrade
+        .iter()
+        .map(Header::fmt)
+        .chain(headers.into_iter().map(|f| Header::fmt(&f)))
+        .collect();
+    (
+        format!("GET /{path} HTTP/1.1\r\nHost: {host}\r\n{headers}\r\n"),
+        sec_key,
+    )
+}
+
+/// Upgrade headers of a client handshake request, with a randomly generated `Sec-WebSocket-Key`.
+///
+/// For http stacks that build the request themselves, the `Host` header is left to them.
+/// Returns the key, to verify the response with [verify_accept_key].
+///
+/// ### Example
+///
+/// ```rust,ignore
+/// let (sec_key, headers) = request_headers();
+/// let mut req = http::Request::get("ws://example.com/chat");
+/// for (name, value) in headers {
+///     req = req.header(name, value);
+/// }
+/// ```
+pub fn request_headers() -> (String, [(&'static str, String); 4]) {
+    let sec_key = base64_encode(rand::random::<[u8; 16]>());
+    let headers = [
+        ("Upgrade", "websocket".into()),
+        ("Connection", "Upgrade".into()),
+        ("Sec-WebSocket-Version", "13".into()),
+        ("Sec-WebSocket-Key", sec_key.clone()),
+    ];
+    (sec_key, headers)
+}
+
+/// Parsed `ws://` or `wss://` URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Url<'a> {
+    /// The scheme is `wss://`.
+    pub secure: bool,
+    /// Host name or IP address, without the brackets of an IPv6 address.
+    pub host: &'a str,
+    /// Explicit port, or the default port of the scheme. (`80` or `443`)
+    pub port: u16,
+    /// Path and query, `/` if empty.
+    pub path: &'a str,
+}
+
+impl<'a> Url<'a> {
+    /// Parse a `ws://` or `wss://` URL.
+    ///
+    /// Returns `None` for other schemes, and URLs with user info or a fragment, which are not allowed in websocket URIs.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use crate::utils::handshake::Url;
+    ///
+    /// let url = Url::parse("ws://example.com:8080/chat?room=1").unwrap();
+    /// assert_eq!((url.host, url.port, url.path), ("example.com", 8080, "/chat?room=1"));
+    /// ```
+    pub fn parse(url: &'a str) -> Option<Self> {
+        let (scheme, rest) = url.split_once("://")?;
+        let (secure, default_port) = match scheme.to_ascii_lowercase().as_str() {
+            "ws" => (false, 80),
+            "wss" => (true, 443),
+            _ => return None,
+        };
+        if rest.contains('#') {
+            return None;
+        }
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(idx) => rest.split_at(idx),
+            None => (rest, "/"),
+        };
+        if authority.contains('@') {
+            return None;
+        }
+        let (host, port) = match authority.strip_prefix('[') {
+            Some(ipv6) => {
+                let (host, port) = ipv6.split_once(']')?;
+                match port {
+                    "" => (host, None),
+                    port => (host, Some(port.strip_prefix(':')?)),
+                }
+            }
+            None => match authority.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            },
+        };
+        let port = match port {
+            Some(port) => port.parse().ok()?,
+            None => default_port,
+        };
+        if host.is_empty() {
+            return None;
+        }
+        Some(Self {
+            secure,
+            host,
+            port,
+            path,
+        })
+    }
+
+    /// Returns the value of the `Host` header: `host:port`, with brackets around an IPv6 address.
+    ///
+    /// The port is omitted if it is the default port of the scheme.
+    pub fn authority(&self) -> String {
+        let host = match self.host.contains(':') {
+            true => format!("[{}]", self.host),
+            false => self.host.to_string(),
+        };
+        match (self.secure, self.port) {
+            (false, 80) | (true, 443) => host,
+            (_, port) => format!("{host}:{port}"),
+        }
+    }
 }
 
 /// Provides a interface for formatting HTTP headers
@@ -165,6 +392,6 @@ impl<K: fmt::Display, V: fmt::Display> Header for (K, V) {
     }
 }
 
-fn base64_encode(string: impl AsRef<[u8]>) -> String {
+pub(super) fn base64_encode(string: impl AsRef<[u8]>) -> String {
     base64::Engine::encode(&base64::prelude::BASE64_STANDARD, string)
 }
@@ -3,7 +3,7 @@ pub mod handshake;
 
 use std::{collections::HashMap, io::Result};
 use tokio::{
-    io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, BufReader},
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, BufReader},
     net::TcpStream,
 };
 use web_socket::WebSocket;
@@ -14,6 +14,16 @@ macro_rules! io_err {
     };
 }
 
+mod builder;
+mod reconnect;
+mod server;
+pub use builder::ClientBuilder;
+pub use reconnect::ReconnectingClient;
+pub use server::ServerBuilder;
+
+/// Parsed http request or response head.
+///
+/// Header names are lowercase. Repeated headers are combined into a comma separated list.
 #[derive(Debug)]
 pub struct Http {
     prefix: String,
@@ -34,45 +44,160 @@ impl std::ops::DerefMut for Http {
     }
 }
 
+/// Default limit of an http message head, see [Http::parse_with_limit]
+pub const MAX_HEAD_LEN: usize = 64 * 1024;
+
 impl Http {
     pub async fn parse<IO>(reader: &mut IO) -> Result<Self>
     where
         IO: Unpin + AsyncBufRead,
     {
-        let mut lines = reader.lines();
+        Self::parse_with_limit(reader, MAX_HEAD_LEN).await
+    }
 
-        let prefix = lines.next_line().await?.unwrap();
+    /// Parse an http message head of at most `limit` bytes, so a peer can't stream headers forever.
+    ///
+    /// The head must end with an empty line. Exceeding `limit` is an `InvalidData` error,
+    /// and the end of the stream before the empty line an `UnexpectedEof` error.
+    pub async fn parse_with_limit<IO>(reader: &mut IO, limit: usize) -> Result<Self>
+    where
+        IO: Unpin + AsyncBufRead,
+    {
+        let mut reader = reader.take(limit as u64);
+        let mut line = String::new();
+        let mut prefix = None;
         let mut headers = HashMap::new();
+        let mut saw_blank_line = false;
 
-        while let Some(line) = lines.next_line().await? {
+        while !saw_blank_line {
+            line.clear();
+            if reader.read_line(&mut line).await? == 0 {
+                if reader.limit() == 0 {
+                    io_err!(InvalidData, "handshake headers too large");
+                }
+                io_err!(UnexpectedEof, "expected http message");
+            }
+            if !line.ends_with('\n') && reader.limit() == 0 {
+                io_err!(InvalidData, "handshake headers too large");
+            }
+            let line = line.trim_end_matches(['\r', '\n']);
+            if prefix.is_none() {
+                prefix = Some(line.to_string());
+                continue;
+            }
             if line == "" {
-                break;
+                saw_blank_line = true;
+                continue;
             }
-            let (key, value) = line.split_once(":").unwrap();
-            headers.insert(key.to_ascii_lowercase(), value.trim_start().into());
+            let Some((key, value)) = line.split_once(":") else {
+                io_err!(InvalidData, "invalid http header");
+            };
+            headers
+                .entry(key.to_ascii_lowercase())
+                .and_modify(|values: &mut String| {
+                    values.push_str(", ");
+                    values.push_str(value.trim());
+                })
+                .or_insert_with(|| value.trim().into());
         }
+        // Always set, the empty line is only checked after the first line.
+        let prefix = prefix.unwrap_or_default();
         Ok(Self { prefix, headers })
     }
+
+    /// Request line or status line, e.g. `HTTP/1.1 101 Switching Protocols`
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
 }
 
 pub async fn connect(addr: &str, path: &str) -> Result<WebSocket<BufReader<TcpStream>>> {
-    let mut stream = BufReader::new(TcpStream::connect(addr).await?);
+    let stream = TcpStream::connect(addr).await?;
+    let (ws, _) = ClientBuilder::new().client(stream, addr, path).await?;
+    Ok(ws)
+}
 
-    let (req, sec_key) = handshake::request(addr, path, [("", "")]);
-    stream.write_all(req.as_bytes()).await?;
+/// Connects to a server listening on the unix socket at `socket`, e.g. a local agent or a sidecar.
+///
+/// The `Host` header is `localhost`, servers accept unix streams with [accept] like any other stream.
+#[cfg(unix)]
+pub async fn connect_unix(
+    socket: impl AsRef<std::path::Path>,
+    path: &str,
+) -> Result<WebSocket<BufReader<tokio::net::UnixStream>>> {
+    let stream = tokio::net::UnixStream::connect(socket).await?;
+    let (ws, _) = ClientBuilder::new()
+        .client(stream, "localhost", path)
+        .await?;
+    Ok(ws)
+}
 
-    let http = Http::parse(&mut stream).await?;
+/// Connects to a `ws://` URL.
+///
+/// `wss://` URLs are rejected, connect a TLS stream and use [client] instead.
+pub async fn connect_url(url: &str) -> Result<WebSocket<BufReader<TcpStream>>> {
+    let (ws, _) = ClientBuilder::new().connect(url).await?;
+    Ok(ws)
+}
 
-    if !http.prefix.starts_with("HTTP/1.1 101 Switching Protocols") {
-        io_err!(InvalidData, "expected upgrade connection");
-    }
-    if http
-        .get("sec-websocket-accept")
-        .expect("couldn't get `sec-websocket-accept` from http response")
-        .ne(&handshake::accept_key_from(sec_key))
-    {
-        io_err!(InvalidData, "invalid websocket accept key");
-    }
+/// Performs the client handshake over an already connected `stream`, e.g. a TLS stream.
+///
+/// Creating the socket is left to the caller, e.g. to bind it to a local interface or set socket options
+/// with `tokio::net::TcpSocket`, or to convert a `socket2::Socket` with `TcpStream::from_std`.
+///
+/// `headers` are added to the request, e.g. `[("Origin", "https://example.com")]` for servers that check the origin.
+///
+/// `protocols` are the offered subprotocols, in order of preference.
+/// The handshake fails if the server selects one that wasn't offered.
+///
+/// Returns the server's response, e.g. to read the selected `sec-websocket-protocol` or a `set-cookie` header.
+/// See [ClientBuilder] for more options.
+pub async fn client<IO>(
+    stream: IO,
+    host: &str,
+    path: &str,
+    headers: &[(&str, &str)],
+    protocols: &[&str],
+) -> Result<(WebSocket<BufReader<IO>>, Http)>
+where
+    IO: Unpin + AsyncRead + AsyncWrite,
+{
+    let builder = headers
+        .iter()
+        .fold(ClientBuilder::new(), |builder, (name, value)| {
+            builder.header(*name, *value)
+        });
+    builder
+        .protocols(protocols.iter().copied())
+        .client(stream, host, path)
+        .await
+}
+
+pub async fn accept<IO>(stream: IO) -> Result<WebSocket<BufReader<IO>>>
+where
+    IO: Unpin + AsyncRead + AsyncWrite,
+{
+    accept_with(stream, &[], |_| None).await
+}
 
-    Ok(WebSocket::client(stream))
+/// Accepts a connection, `select` picks a subprotocol from the ones offered by the client.
+///
+/// `select` isn't called if the client didn't offer any subprotocol.
+///
+/// `headers` are added to the `101` response, e.g. `[("Set-Cookie", "session=1")]`.
+/// The `Sec-WebSocket-Accept` header is computed from the request. See [ServerBuilder] for more options.
+pub async fn accept_with<IO>(
+    stream: IO,
+    headers: &[(&str, &str)],
+    select: impl for<'a> FnOnce(&[&'a str]) -> Option<&'a str>,
+) -> Result<WebSocket<BufReader<IO>>>
+where
+    IO: Unpin + AsyncRead + AsyncWrite,
+{
+    let builder = headers
+        .iter()
+        .fold(ServerBuilder::new(), |builder, (name, value)| {
+            builder.header(*name, *value)
+        });
+    builder.accept_with(stream, select).await
 }
@@ -0,0 +1,184 @@
+use super::ClientBuilder;
+use futures_util::Stream;
+use std::{
+    io::{Error, ErrorKind, Result},
+    time::Duration,
+};
+use tokio::{io::BufReader, net::TcpStream};
+use web_socket::{Frame, Message, WebSocket};
+
+/// A client that reconnects to a `ws://` URL whenever the connection is lost, e.g. for telemetry or chat.
+///
+/// The connection is considered lost on an error or a Close frame. It's reestablished with an exponential backoff
+/// and jitter, unless a message was received since the previous attempt. The subscribe messages are sent again
+/// on every new connection. Set a [ClientBuilder::keepalive] to detect dead connections.
+///
+/// Only transient errors are retried, e.g. a refused connection while the server restarts.
+/// Other errors, such as an invalid URL or a failed handshake, are returned right away.
+///
+/// ### Example
+///
+/// ```no_run
+/// use crate::utils::ReconnectingClient;
+/// use futures_util::StreamExt;
+///
+/// # async {
+/// let messages = ReconnectingClient::new("ws://example.com/feed")
+///     .subscribe(r#"{"subscribe": "prices"}"#)
+///     .into_stream();
+///
+/// let mut messages = std::pin::pin!(messages);
+/// while let Some(msg) = messages.next().await {
+///     println!("{:?}", msg?);
+/// }
+/// # std::io::Result::Ok(())
+/// # };
+/// ```
+pub struct ReconnectingClient {
+    url: String,
+    builder: ClientBuilder,
+    subscriptions: Vec<String>,
+    min_delay: Duration,
+    max_delay: Duration,
+    max_attempts: Option<u32>,
+    /// Connection attempts since a message was last received.
+    attempts: u32,
+    ws: Option<WebSocket<BufReader<TcpStream>>>,
+}
+
+impl ReconnectingClient {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            builder: ClientBuilder::new(),
+            subscriptions: Vec::new(),
+            min_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(30),
+            max_attempts: None,
+            attempts: 0,
+            ws: None,
+        }
+    }
+
+    /// Options of the connection, e.g. headers or subprotocols.
+    pub fn builder(mut self, builder: ClientBuilder) -> Self {
+        self.builder = builder;
+        self
+    }
+
+    /// Add a text message sent as soon as the client is connected, and again after every reconnection.
+    pub fn subscribe(mut self, msg: impl Into<String>) -> Self {
+        self.subscriptions.push(msg.into());
+        self
+    }
+
+    /// Delay before the second attempt to connect, doubled by every following attempt up to `max`.
+    ///
+    /// A random jitter of up to half the delay is subtracted, so that clients don't reconnect all at once.
+    ///
+    /// Default: `100ms` to `30s`
+    pub fn backoff(mut self, min: Duration, max: Duration) -> Self {
+        self.min_delay = min;
+        self.max_delay = max;
+        self
+    }
+
+    /// Maximum number of attempts to connect since a message was last received,
+    /// after which the error of the last attempt is returned.
+    ///
+    /// Default: unlimited
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = Some(attempts);
+        self
+    }
+
+    /// Receives the next message, reconnecting as many times as needed.
+    ///
+    /// Close frames and errors of an established connection aren't returned, they end the connection and a new one is established.
+    /// Returns an error if connecting fails with a permanent error, or more than [ReconnectingClient::max_attempts] times.
+    pub async fn recv(&mut self) -> Result<Message> {
+        loop {
+            let ws = self.connection().await?;
+            match ws.recv_message().await {
+                Ok(Message::Close { .. } | Message::Closed) | Err(_) => self.ws = None,
+                Ok(msg) => {
+                    self.attempts = 0;
+                    return Ok(msg);
+                }
+            }
+        }
+    }
+
+    /// Sends a message over the current connection, connecting first if needed.
+    ///
+    /// Messages aren't resent, if the connection is lost the error is returned and the next call reconnects.
+    pub async fn send(&mut self, msg: impl Into<Frame<'_>>) -> Result<()> {
+        let ws = self.connection().await?;
+        let res = ws.send(msg).await;
+        if res.is_err() {
+            self.ws = None;
+        }
+        res
+    }
+
+    /// Converts into a [Stream] of the received messages, that ends after the first error of [ReconnectingClient::recv].
+    pub fn into_stream(self) -> impl Stream<Item = Result<Message>> {
+        futures_util::stream::unfold(Some(self), |client| async move {
+            let mut client = client?;
+            match client.recv().await {
+                Ok(msg) => Some((Ok(msg), Some(client))),
+                Err(err) => Some((Err(err), None)),
+            }
+        })
+    }
+
+    async fn connection(&mut self) -> Result<&mut WebSocket<BufReader<TcpStream>>> {
+        while self.ws.is_none() {
+            if self.attempts > 0 {
+                tokio::time::sleep(self.delay()).await;
+            }
+            self.attempts = self.attempts.saturating_add(1);
+            match self.connect().await {
+                Ok(ws) => self.ws = Some(ws),
+                Err(err)
+                    if is_transient(&err)
+                        && self.max_attempts.is_none_or(|max| self.attempts < max) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(self.ws.as_mut().unwrap())
+    }
+
+    async fn connect(&self) -> Result<WebSocket<BufReader<TcpStream>>> {
+        let (mut ws, _) = self.builder.connect(&self.url).await?;
+        for msg in &self.subscriptions {
+            ws.send(msg.as_str()).await?;
+        }
+        ws.flush().await?;
+        Ok(ws)
+    }
+
+    fn delay(&self) -> Duration {
+        let factor = 1_u32 << (self.attempts - 1).min(16);
+        let delay = self.min_delay.saturating_mul(factor).min(self.max_delay);
+        delay.mul_f64(1.0 - rand::random::<f64>() / 2.0)
+    }
+}
+
+/// Errors after which connecting again may succeed, e.g. while the server restarts or the network is down.
+fn is_transient(err: &Error) -> bool {
+    matches!(
+        err.kind(),
+        ErrorKind::ConnectionRefused
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::BrokenPipe
+            | ErrorKind::TimedOut
+            | ErrorKind::UnexpectedEof
+            | ErrorKind::Interrupted
+            | ErrorKind::HostUnreachable
+            | ErrorKind::NetworkUnreachable
+            | ErrorKind::NetworkDown
+    )
+}
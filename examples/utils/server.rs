@@ -0,0 +1,181 @@
+use super::{handshake, Http, MAX_HEAD_LEN};
+use std::{fmt, io::Result, sync::Arc};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use web_socket::WebSocket;
+#[cfg(feature = "deflate")]
+use web_socket::{deflate::DeflateConfig, Role};
+
+type CheckOrigin = Arc<dyn Fn(Option<&str>) -> bool + Send + Sync>;
+
+/// Configuration of the server handshake.
+///
+/// The defaults are the same as those of [super::accept].
+/// It's cheap to clone, e.g. to share it with the task of every connection.
+///
+/// ### Example
+///
+/// ```no_run
+/// use crate::utils::ServerBuilder;
+/// use tokio::net::TcpListener;
+///
+/// # async {
+/// let server = ServerBuilder::new()
+///     .header("Set-Cookie", "session=1")
+///     .check_origin(|origin| origin == Some("https://example.com"));
+///
+/// let listener = TcpListener::bind("localhost:8080").await?;
+/// let (stream, _) = listener.accept().await?;
+/// let ws = server.accept(stream).await?;
+/// # std::io::Result::Ok(())
+/// # };
+/// ```
+#[derive(Clone)]
+pub struct ServerBuilder {
+    headers: Vec<(String, String)>,
+    check_origin: Option<CheckOrigin>,
+    max_head_len: usize,
+}
+
+impl Default for ServerBuilder {
+    fn default() -> Self {
+        Self {
+            headers: Vec::new(),
+            check_origin: None,
+            max_head_len: MAX_HEAD_LEN,
+        }
+    }
+}
+
+impl fmt::Debug for ServerBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerBuilder")
+            .field("headers", &self.headers)
+            .field("check_origin", &self.check_origin.is_some())
+            .field("max_head_len", &self.max_head_len)
+            .finish()
+    }
+}
+
+impl ServerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a header to the `101` response, e.g. `Set-Cookie`.
+    ///
+    /// The `Sec-WebSocket-Accept` header is computed from the request.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Rejects the handshake with `403 Forbidden` if `check` returns `false` for the `Origin` header of the request.
+    ///
+    /// Browsers send cookies and other credentials with a websocket handshake, whatever page opened it,
+    /// and the same-origin policy doesn't apply to websockets.
+    /// Without this check, a malicious site can open a connection with the credentials of its visitors (Cross-Site WebSocket Hijacking).
+    ///
+    /// `check` receives `None` if the request has no `Origin` header, which is the case of most non-browser clients.
+    /// Those can send any origin, so this isn't a substitute for authentication.
+    pub fn check_origin(
+        mut self,
+        check: impl Fn(Option<&str>) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.check_origin = Some(Arc::new(check));
+        self
+    }
+
+    /// Maximum length of the handshake request head, see [Http::parse_with_limit].
+    ///
+    /// Default: [MAX_HEAD_LEN]
+    pub fn max_head_len(mut self, len: usize) -> Self {
+        self.max_head_len = len;
+        self
+    }
+
+    /// Accepts a connection, without selecting a subprotocol.
+    pub async fn accept<IO>(&self, stream: IO) -> Result<WebSocket<BufReader<IO>>>
+    where
+        IO: Unpin + AsyncRead + AsyncWrite,
+    {
+        self.accept_with(stream, |_| None).await
+    }
+
+    /// Accepts a connection, `select` picks a subprotocol from the ones offered by the client.
+    ///
+    /// `select` isn't called if the client didn't offer any subprotocol.
+    pub async fn accept_with<IO>(
+        &self,
+        stream: IO,
+        select: impl for<'a> FnOnce(&[&'a str]) -> Option<&'a str>,
+    ) -> Result<WebSocket<BufReader<IO>>>
+    where
+        IO: Unpin + AsyncRead + AsyncWrite,
+    {
+        let mut stream = BufReader::new(stream);
+        let http = Http::parse_with_limit(&mut stream, self.max_head_len).await?;
+
+        // The only version this endpoint speaks is `13`, the client may retry the handshake with it.
+        if http
+            .get("sec-websocket-version")
+            .is_some_and(|version| version.trim() != "13")
+        {
+            stream
+                .write_all(b"HTTP/1.1 426 Upgrade Required\r\nSec-WebSocket-Version: 13\r\n\r\n")
+                .await?;
+            io_err!(InvalidData, "unsupported websocket version");
+        }
+        let Some(key) = get_sec_key(&http) else {
+            stream
+                .write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n")
+                .await?;
+            io_err!(InvalidData, "expected websocket upgrade request");
+        };
+        if let Some(check) = &self.check_origin {
+            if !check(http.get("origin").map(String::as_str)) {
+                stream.write_all(b"HTTP/1.1 403 Forbidden\r\n\r\n").await?;
+                io_err!(PermissionDenied, "origin not allowed");
+            }
+        }
+        #[cfg(feature = "deflate")]
+        let deflate = http
+            .get("sec-websocket-extensions")
+            .and_then(|offers| DeflateConfig::negotiate(offers, Role::Server));
+        #[cfg(not(feature = "deflate"))]
+        let deflate = None::<&str>;
+
+        let offers: Vec<&str> = match http.get("sec-websocket-protocol") {
+            Some(offers) => offers.split(',').map(str::trim).collect(),
+            None => vec![],
+        };
+        let protocol = match offers.is_empty() {
+            true => None,
+            false => select(&offers),
+        };
+
+        let extensions = deflate.map(|config| ("Sec-WebSocket-Extensions", config.to_string()));
+        let protocol = protocol.map(|protocol| ("Sec-WebSocket-Protocol", protocol.to_string()));
+        let headers = self
+            .headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.clone()));
+        let res = handshake::response(key, extensions.into_iter().chain(protocol).chain(headers));
+        stream.write_all(res.as_bytes()).await?;
+
+        let mut ws = WebSocket::server(stream);
+        #[cfg(feature = "deflate")]
+        if let Some(config) = deflate {
+            ws.enable_deflate(config);
+        }
+        Ok(ws)
+    }
+}
+
+fn get_sec_key(http: &Http) -> Option<&String> {
+    if !http.prefix().starts_with("GET ")
+        || !handshake::is_upgrade(|name| http.get(name).map(String::as_str))
+    {
+        return None;
+    }
+    http.get("sec-websocket-key")
+}
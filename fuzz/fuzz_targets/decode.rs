@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use web_socket::decode;
+
+fuzz_target!(|buf: &[u8]| {
+    let Ok(Some(frame)) = decode(buf) else {
+        return;
+    };
+    assert!(frame.offset <= buf.len());
+    assert!(matches!(frame.offset, 2 | 4 | 6 | 8 | 10 | 14));
+    assert_eq!(frame.mask.is_some(), buf[1] & 0x80 != 0);
+    if let Some(payload) = frame.payload(buf) {
+        assert_eq!(payload.len(), frame.len);
+    }
+});
@@ -0,0 +1,36 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use web_socket::{decode, Frame, OpCode};
+
+const OPCODES: [OpCode; 6] = [
+    OpCode::Continuation,
+    OpCode::Text,
+    OpCode::Binary,
+    OpCode::Close,
+    OpCode::Ping,
+    OpCode::Pong,
+];
+
+fuzz_target!(|input: (bool, u8, Option<[u8; 4]>, &[u8])| {
+    let (fin, opcode, mask, data) = input;
+    let opcode = OPCODES[opcode as usize % OPCODES.len()];
+    let frame = Frame { fin, opcode, data };
+
+    let mut buf = Vec::new();
+    let len = frame.encode_to(mask, &mut buf);
+    assert_eq!(len, buf.len());
+    assert_eq!(len, frame.encoded_len(mask.is_some()));
+
+    let decoded = decode(&buf).unwrap().unwrap();
+    assert_eq!((decoded.fin, decoded.rsv, decoded.opcode), (fin, 0, opcode));
+    assert_eq!((decoded.mask, decoded.len), (mask, data.len()));
+
+    let mut payload = decoded.payload(&buf).unwrap().to_vec();
+    if let Some(mask) = mask {
+        for (byte, key) in payload.iter_mut().zip(mask.iter().cycle()) {
+            *byte ^= key;
+        }
+    }
+    assert_eq!(payload, data);
+});
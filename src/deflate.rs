@@ -0,0 +1,307 @@
+//! [permessage-deflate](https://datatracker.ietf.org/doc/html/rfc7692) extension.
+//!
+//! The extension is negotiated during the handshake with the `Sec-WebSocket-Extensions` header.
+//! Once negotiated, enable it with [WebSocket::enable_deflate](crate::WebSocket::enable_deflate),
+//! which adds it as an [Extension] that uses the `RSV1` bit.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use web_socket::{deflate::DeflateConfig, Role};
+//!
+//! // server side: select an offer from the client's request header
+//! let offer = "permessage-deflate; client_max_window_bits, permessage-deflate";
+//! let config = DeflateConfig::negotiate(offer, Role::Server).unwrap();
+//!
+//! // ...and send it back in the response header.
+//! assert_eq!(config.to_string(), "permessage-deflate");
+//! ```
+
+use crate::{
+    extension::{Extension, Incoming, Outgoing},
+    ProtocolError, Role, RSV1,
+};
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+use std::fmt;
+
+/// Negotiated parameters of the `permessage-deflate` extension.
+///
+/// Only a LZ77 sliding window of 15 bits is supported for compression. Offers that
+/// require a smaller window for the messages we send are declined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeflateConfig {
+    /// The server resets its compression context after each message.
+    pub server_no_context_takeover: bool,
+    /// The client resets its compression context after each message.
+    pub client_no_context_takeover: bool,
+}
+
+impl DeflateConfig {
+    /// Select the first acceptable `permessage-deflate` offer from a `Sec-WebSocket-Extensions` header value.
+    ///
+    /// A server calls this with the client's offers. A client calls it with the server's response,
+    /// in which case `None` means that the handshake should fail.
+    pub fn negotiate(header: &str, role: Role) -> Option<Self> {
+        header
+            .split(',')
+            .find_map(|offer| Self::parse_offer(offer, role))
+    }
+
+    fn parse_offer(offer: &str, role: Role) -> Option<Self> {
+        let mut params = offer.split(';').map(str::trim);
+        if !params.next()?.eq_ignore_ascii_case("permessage-deflate") {
+            return None;
+        }
+        let mut config = Self::default();
+        let (mut server_max_window_bits, mut client_max_window_bits) = (None, None);
+
+        for param in params {
+            let (name, value) = match param.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (param, None),
+            };
+            let (seen, window_bits) = match name {
+                "server_no_context_takeover" if value.is_none() => (
+                    std::mem::replace(&mut config.server_no_context_takeover, true),
+                    None,
+                ),
+                "client_no_context_takeover" if value.is_none() => (
+                    std::mem::replace(&mut config.client_no_context_takeover, true),
+                    None,
+                ),
+                "server_max_window_bits" => {
+                    let bits = window_bits(value?)?;
+                    (server_max_window_bits.replace(bits).is_some(), Some(bits))
+                }
+                // May be sent by a client without a value, to indicate that it supports the parameter.
+                "client_max_window_bits" => {
+                    let bits = value.map_or(Some(15), window_bits)?;
+                    (client_max_window_bits.replace(bits).is_some(), Some(bits))
+                }
+                _ => return None,
+            };
+            if seen || window_bits.is_some_and(|bits| !(8..=15).contains(&bits)) {
+                return None;
+            }
+        }
+        let own_max_window_bits = match role {
+            Role::Server => server_max_window_bits,
+            Role::Client => client_max_window_bits,
+        };
+        if own_max_window_bits.is_some_and(|bits| bits < 15) {
+            return None;
+        }
+        Some(config)
+    }
+
+    fn own_no_context_takeover(&self, role: Role) -> bool {
+        match role {
+            Role::Server => self.server_no_context_takeover,
+            Role::Client => self.client_no_context_takeover,
+        }
+    }
+
+    fn peer_no_context_takeover(&self, role: Role) -> bool {
+        match role {
+            Role::Server => self.client_no_context_takeover,
+            Role::Client => self.server_no_context_takeover,
+        }
+    }
+}
+
+fn window_bits(value: &str) -> Option<u8> {
+    value.parse().ok()
+}
+
+/// Formats as the `Sec-WebSocket-Extensions` header value.
+impl fmt::Display for DeflateConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("permessage-deflate")?;
+        if self.server_no_context_takeover {
+            f.write_str("; server_no_context_takeover")?;
+        }
+        if self.client_no_context_takeover {
+            f.write_str("; client_no_context_takeover")?;
+        }
+        Ok(())
+    }
+}
+
+/// The `permessage-deflate` extension, added by [WebSocket::enable_deflate](crate::WebSocket::enable_deflate).
+pub(crate) struct PerMessageDeflate {
+    config: DeflateConfig,
+    role: Role,
+}
+
+impl PerMessageDeflate {
+    pub fn new(config: DeflateConfig, role: Role) -> Self {
+        Self { config, role }
+    }
+}
+
+impl Extension for PerMessageDeflate {
+    type Outgoing = Deflate;
+    type Incoming = Inflate;
+
+    fn rsv(&self) -> u8 {
+        RSV1
+    }
+
+    fn split(self) -> (Deflate, Inflate) {
+        (
+            Deflate::new(self.config, self.role),
+            Inflate::new(self.config, self.role),
+        )
+    }
+}
+
+impl Outgoing for Deflate {
+    fn transform_outgoing(
+        &mut self,
+        data: &[u8],
+        rsv: &mut u8,
+    ) -> std::io::Result<Option<Vec<u8>>> {
+        let data = self.compress(data)?;
+        *rsv |= RSV1;
+        Ok(Some(data))
+    }
+}
+
+impl Incoming for Inflate {
+    fn transform_incoming(
+        &mut self,
+        data: &[u8],
+        rsv: u8,
+        fin: bool,
+        limit: usize,
+    ) -> Result<Option<Vec<u8>>, ProtocolError> {
+        if rsv & RSV1 == 0 {
+            return Ok(None);
+        }
+        self.decompress(data, fin, limit).map(Some)
+    }
+}
+
+/// Compression state of the messages that are sent.
+pub(crate) struct Deflate {
+    /// Reset the compression context after each message.
+    no_context_takeover: bool,
+    compress: Compress,
+}
+
+impl fmt::Debug for Deflate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Deflate")
+            .field("no_context_takeover", &self.no_context_takeover)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Deflate {
+    pub fn new(config: DeflateConfig, role: Role) -> Self {
+        Self {
+            no_context_takeover: config.own_no_context_takeover(role),
+            compress: Compress::new(Compression::default(), false),
+        }
+    }
+
+    /// Compress the payload of a complete message.
+    pub fn compress(&mut self, mut data: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(data.len() / 2 + 64);
+        loop {
+            let total_in = self.compress.total_in();
+            self.compress
+                .compress_vec(data, &mut out, FlushCompress::Sync)
+                .map_err(std::io::Error::other)?;
+            data = &data[(self.compress.total_in() - total_in) as usize..];
+            // The flush is complete, once there is space left in the output buffer.
+            if data.is_empty() && out.len() < out.capacity() {
+                break;
+            }
+            out.reserve(out.capacity());
+        }
+        // Remove the empty stored block (`0x00 0x00 0xff 0xff`) emitted by the sync flush.
+        if out.ends_with(&[0, 0, 0xff, 0xff]) {
+            out.truncate(out.len() - 4);
+        }
+        if self.no_context_takeover {
+            self.compress.reset();
+        }
+        Ok(out)
+    }
+}
+
+/// Decompression state of the messages that are received.
+pub(crate) struct Inflate {
+    /// The endpoint resets its compression context after each message.
+    no_context_takeover: bool,
+    decompress: Decompress,
+}
+
+impl fmt::Debug for Inflate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Inflate")
+            .field("no_context_takeover", &self.no_context_takeover)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Inflate {
+    pub fn new(config: DeflateConfig, role: Role) -> Self {
+        Self {
+            no_context_takeover: config.peer_no_context_takeover(role),
+            decompress: Decompress::new(false),
+        }
+    }
+
+    /// Decompress a fragment of a compressed message, `fin` marks the last fragment.
+    pub fn decompress(
+        &mut self,
+        data: &[u8],
+        fin: bool,
+        limit: usize,
+    ) -> Result<Vec<u8>, ProtocolError> {
+        let mut out = Vec::with_capacity((data.len() * 2).min(limit) + 64);
+        self.inflate(data, &mut out, limit)?;
+        if fin {
+            self.inflate(&[0, 0, 0xff, 0xff], &mut out, limit)?;
+            if self.no_context_takeover {
+                self.decompress.reset(false);
+            }
+        }
+        Ok(out)
+    }
+
+    fn inflate(
+        &mut self,
+        mut data: &[u8],
+        out: &mut Vec<u8>,
+        limit: usize,
+    ) -> Result<(), ProtocolError> {
+        loop {
+            let total_in = self.decompress.total_in();
+            let status = self
+                .decompress
+                .decompress_vec(data, out, FlushDecompress::Sync)
+                .map_err(|_| ProtocolError::InvalidCompressedPayload)?;
+            data = &data[(self.decompress.total_in() - total_in) as usize..];
+
+            if out.len() > limit {
+                return Err(ProtocolError::PayloadTooLarge);
+            }
+            match status {
+                // The peer ended the deflate stream, the next message starts a new one.
+                Status::StreamEnd => {
+                    self.decompress.reset(false);
+                    return Ok(());
+                }
+                Status::BufError => return Ok(()),
+                Status::Ok => {}
+            }
+            if data.is_empty() && out.len() < out.capacity() {
+                return Ok(());
+            }
+            out.reserve(out.capacity());
+        }
+    }
+}
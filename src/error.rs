@@ -0,0 +1,87 @@
+use crate::CloseCode;
+use core::fmt;
+
+/// Violation of the websocket protocol by the endpoint, returned as [Event::Error](crate::Event::Error).
+///
+/// [WebSocket::recv_message](crate::WebSocket::recv_message) and the `Stream` of messages
+/// return it as the inner error of an `InvalidData` [std::io::Error], so it can be told apart from a failure of the stream:
+///
+/// ```rust
+/// use web_socket::ProtocolError;
+///
+/// fn protocol_error(err: &std::io::Error) -> Option<ProtocolError> {
+///     err.get_ref()?.downcast_ref().copied()
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolError {
+    /// A reserved bit is set, without an extension that defines its meaning.
+    ReservedBits,
+    /// A server received an unmasked frame.
+    ExpectedMaskedFrame,
+    /// A client received a masked frame.
+    ExpectedUnmaskedFrame,
+    /// The opcode is reserved: `3-7` and `11-15`.
+    UnknownOpCode,
+    /// A control frame without the `FIN` bit.
+    FragmentedControlFrame,
+    /// A control frame with a payload longer than 125 bytes.
+    ControlFrameTooLarge,
+    /// A continuation frame without a message in progress, or a new data frame while a message is in progress.
+    InvalidDataFrame,
+    /// The payload of a frame exceeds [WebSocket::max_payload_len](crate::WebSocket::max_payload_len).
+    PayloadTooLarge,
+    /// A 64-bit payload length with the most significant bit set.
+    InvalidPayloadLen,
+    /// A fragmented message exceeds [WebSocket::max_message_len](crate::WebSocket::max_message_len).
+    MessageTooLarge,
+    /// A text message, or the reason of a Close frame, isn't valid UTF-8.
+    InvalidUtf8,
+    /// The status code of a Close frame is truncated to a single byte, or must not be sent by an endpoint.
+    InvalidCloseCode,
+    /// The payload of a compressed message can't be decompressed.
+    InvalidCompressedPayload,
+}
+
+impl ProtocolError {
+    /// Status code of the Close frame sent in response to the violation.
+    ///
+    /// - [CloseCode::InvalidPayload] (`1007`) for invalid UTF-8.
+    /// - [CloseCode::MessageTooBig] (`1009`) for exceeded limits.
+    /// - [CloseCode::ProtocolError] (`1002`) otherwise.
+    pub fn close_code(self) -> CloseCode {
+        match self {
+            ProtocolError::InvalidUtf8 => CloseCode::InvalidPayload,
+            ProtocolError::PayloadTooLarge | ProtocolError::MessageTooLarge => {
+                CloseCode::MessageTooBig
+            }
+            _ => CloseCode::ProtocolError,
+        }
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ProtocolError::ReservedBits => "reserve bit must be `0`",
+            ProtocolError::ExpectedMaskedFrame => "expected masked frame",
+            ProtocolError::ExpectedUnmaskedFrame => "expected unmasked frame",
+            ProtocolError::UnknownOpCode => "unknown opcode",
+            ProtocolError::FragmentedControlFrame => "control frame must not be fragmented",
+            ProtocolError::ControlFrameTooLarge => {
+                "control frame must have a payload length of 125 bytes or less"
+            }
+            ProtocolError::InvalidDataFrame => "invalid data frame",
+            ProtocolError::PayloadTooLarge => "payload too large",
+            ProtocolError::InvalidPayloadLen => {
+                "most significant bit of the payload length must be `0`"
+            }
+            ProtocolError::MessageTooLarge => "message too large",
+            ProtocolError::InvalidUtf8 => "invalid utf-8 payload",
+            ProtocolError::InvalidCloseCode => "invalid close code",
+            ProtocolError::InvalidCompressedPayload => "invalid compressed payload",
+        })
+    }
+}
+
+impl core::error::Error for ProtocolError {}
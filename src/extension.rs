@@ -0,0 +1,169 @@
+//! Extensions that transform the payload of messages, negotiated during the handshake with the `Sec-WebSocket-Extensions` header.
+//!
+//! Extensions are added to a websocket with [WebSocket::add_extension](crate::WebSocket::add_extension),
+//! `permessage-deflate` is one of them, with the `deflate` feature.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use web_socket::{extension::*, ProtocolError, WebSocket, RSV2};
+//!
+//! /// Reverses the payload of the messages that have the `RSV2` bit set.
+//! #[derive(Clone, Copy)]
+//! struct Reverse;
+//!
+//! impl Extension for Reverse {
+//!     type Outgoing = Self;
+//!     type Incoming = Self;
+//!
+//!     fn rsv(&self) -> u8 {
+//!         RSV2
+//!     }
+//!
+//!     fn split(self) -> (Self, Self) {
+//!         (self, self)
+//!     }
+//! }
+//!
+//! impl Outgoing for Reverse {
+//!     fn transform_outgoing(&mut self, data: &[u8], rsv: &mut u8) -> std::io::Result<Option<Vec<u8>>> {
+//!         *rsv |= RSV2;
+//!         Ok(Some(data.iter().rev().copied().collect()))
+//!     }
+//! }
+//!
+//! impl Incoming for Reverse {
+//!     fn transform_incoming(
+//!         &mut self,
+//!         data: &[u8],
+//!         rsv: u8,
+//!         _fin: bool,
+//!         _limit: usize,
+//!     ) -> Result<Option<Vec<u8>>, ProtocolError> {
+//!         Ok((rsv & RSV2 != 0).then(|| data.iter().rev().copied().collect()))
+//!     }
+//! }
+//!
+//! let mut ws = WebSocket::client(Vec::<u8>::new());
+//! ws.add_extension(Reverse);
+//! ```
+
+use crate::ProtocolError;
+use std::{fmt, io::Result};
+
+/// An extension, e.g. a compression of the messages.
+///
+/// Only complete text and binary messages are transformed when they are sent, by [WebSocket::send](crate::WebSocket::send),
+/// [WebSocket::send_fragmented](crate::WebSocket::send_fragmented) and [WebSocket::send_all](crate::WebSocket::send_all).
+/// Fragments sent one by one and control frames are sent as they are.
+///
+/// The reserved bits are only set on the first frame of a message.
+/// A continuation frame, or a control frame, with reserved bits set fails the connection.
+pub trait Extension {
+    /// State of the messages that are sent.
+    type Outgoing: Outgoing + 'static;
+    /// State of the messages that are received.
+    type Incoming: Incoming + 'static;
+
+    /// Reserved bits of the frame header defined by the extension, at their header position. ([RSV1](crate::RSV1), [RSV2](crate::RSV2), [RSV3](crate::RSV3))
+    ///
+    /// A frame with other reserved bits set fails the connection.
+    fn rsv(&self) -> u8;
+
+    /// Splits the extension into the states of both directions.
+    ///
+    /// They are independent, so that both halves of [WebSocket::split](crate::WebSocket::split) don't wait for each other:
+    /// the write half keeps the outgoing state, and the read half the incoming one.
+    fn split(self) -> (Self::Outgoing, Self::Incoming);
+}
+
+/// Outgoing state of an [Extension].
+pub trait Outgoing: Send {
+    /// Transforms the payload of a message that is sent, `rsv` are the reserved bits of its first frame.
+    ///
+    /// Returns `None` if the payload is sent as it is.
+    fn transform_outgoing(&mut self, data: &[u8], rsv: &mut u8) -> Result<Option<Vec<u8>>>;
+}
+
+/// Incoming state of an [Extension].
+pub trait Incoming: Send {
+    /// Transforms the payload of a data frame that is received.
+    ///
+    /// `rsv` are the reserved bits of the first frame of the message, and `fin` marks its last frame.
+    /// The transformed payload must not exceed `limit` bytes, otherwise it's a [ProtocolError::PayloadTooLarge] error.
+    ///
+    /// Returns `None` if the payload is received as it is.
+    fn transform_incoming(
+        &mut self,
+        data: &[u8],
+        rsv: u8,
+        fin: bool,
+        limit: usize,
+    ) -> std::result::Result<Option<Vec<u8>>, ProtocolError>;
+}
+
+/// Outgoing states of the extensions of a websocket, applied in the order they were added.
+#[derive(Default)]
+pub(crate) struct OutgoingExtensions(Vec<Box<dyn Outgoing>>);
+
+/// Incoming states of the extensions of a websocket, applied in the reverse order they were added.
+#[derive(Default)]
+pub(crate) struct IncomingExtensions(Vec<Box<dyn Incoming>>);
+
+impl fmt::Debug for OutgoingExtensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OutgoingExtensions")
+            .field("len", &self.0.len())
+            .finish()
+    }
+}
+
+impl fmt::Debug for IncomingExtensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IncomingExtensions")
+            .field("len", &self.0.len())
+            .finish()
+    }
+}
+
+impl OutgoingExtensions {
+    pub fn push(&mut self, extension: impl Outgoing + 'static) {
+        self.0.push(Box::new(extension));
+    }
+
+    /// Returns the transformed payload of a message that is sent, with the reserved bits of its first frame.
+    pub fn transform(&mut self, data: &[u8]) -> Result<(Option<Vec<u8>>, u8)> {
+        let (mut out, mut rsv) = (None, 0);
+        for extension in self.0.iter_mut() {
+            let data = out.as_deref().unwrap_or(data);
+            if let Some(data) = extension.transform_outgoing(data, &mut rsv)? {
+                out = Some(data);
+            }
+        }
+        Ok((out, rsv))
+    }
+}
+
+impl IncomingExtensions {
+    pub fn push(&mut self, extension: impl Incoming + 'static) {
+        self.0.push(Box::new(extension));
+    }
+
+    /// Returns the transformed payload of a data frame that is received.
+    pub fn transform(
+        &mut self,
+        data: &[u8],
+        rsv: u8,
+        fin: bool,
+        limit: usize,
+    ) -> std::result::Result<Option<Vec<u8>>, ProtocolError> {
+        let mut out = None;
+        for extension in self.0.iter_mut().rev() {
+            let data = out.as_deref().unwrap_or(data);
+            if let Some(data) = extension.transform_incoming(data, rsv, fin, limit)? {
+                out = Some(data);
+            }
+        }
+        Ok(out)
+    }
+}
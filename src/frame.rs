@@ -1,30 +1,143 @@
 #![doc(hidden)]
+#![allow(clippy::unusual_byte_groupings)]
+use crate::{mask::unmask, OpCode, ProtocolError};
+use alloc::{borrow::Cow, collections::VecDeque, string::String, vec::Vec};
+
+/// `RSV1` bit of the frame header, set on the first frame of a compressed message by `permessage-deflate`.
+pub const RSV1: u8 = 0x40;
+/// `RSV2` bit of the frame header.
+pub const RSV2: u8 = 0x20;
+/// `RSV3` bit of the frame header.
+pub const RSV3: u8 = 0x10;
 
 pub struct Frame<'a> {
     pub fin: bool,
-    pub opcode: u8,
+    pub opcode: OpCode,
     pub data: &'a [u8],
 }
 
 impl<'a> Frame<'a> {
     #[inline]
     pub fn encode_without_mask(self) -> Vec<u8> {
-        let mut buf = Vec::<u8>::with_capacity(10 + self.data.len());
-        unsafe {
-            let dist = buf.as_mut_ptr();
-            let head_len = self.encode_header_unchecked(dist, 0);
-            std::ptr::copy_nonoverlapping(self.data.as_ptr(), dist.add(head_len), self.data.len());
-            buf.set_len(head_len + self.data.len());
-        }
+        let mut buf = Vec::new();
+        self.encode_without_mask_into(&mut buf);
         buf
     }
 
     #[inline]
     pub fn encode_with(self, mask: [u8; 4]) -> Vec<u8> {
-        let mut buf = Vec::<u8>::with_capacity(14 + self.data.len());
+        let mut buf = Vec::new();
+        self.encode_with_into(mask, &mut buf);
+        buf
+    }
+
+    /// Appends the encoded frame to `buf`, returns the number of bytes written.
+    #[inline]
+    pub fn encode_without_mask_into(&self, buf: &mut Vec<u8>) -> usize {
+        self.write_without_mask(0, buf);
+        self.encoded_len(false)
+    }
+
+    /// Appends the encoded and masked frame to `buf`, returns the number of bytes written.
+    #[inline]
+    pub fn encode_with_into(&self, mask: [u8; 4], buf: &mut Vec<u8>) -> usize {
+        self.write_with(0, mask, buf);
+        self.encoded_len(true)
+    }
+
+    /// Appends the encoded frame to `buf` with the reserved bits `rsv` set, masked if `mask` is `Some`.
+    /// Returns the number of bytes written.
+    ///
+    /// `rsv` is at its header position, like [DecodedFrame::rsv], e.g. [RSV1] for a compressed message.
+    ///
+    /// Panics if `rsv` has other bits set.
+    #[inline]
+    pub fn encode_with_rsv_into(&self, rsv: u8, mask: Option<[u8; 4]>, buf: &mut Vec<u8>) -> usize {
+        assert_eq!(rsv & !(RSV1 | RSV2 | RSV3), 0, "invalid rsv bits");
+        match mask {
+            Some(mask) => self.write_with(rsv, mask, buf),
+            None => self.write_without_mask(rsv, buf),
+        }
+        self.encoded_len(mask.is_some())
+    }
+
+    /// Appends the encoded frame to any [FrameSink], masked if `mask` is `Some`.
+    /// Returns the number of bytes written.
+    ///
+    /// [Frame::encode_without_mask_into] and [Frame::encode_with_into] are faster for a `Vec<u8>`.
+    pub fn encode_to<S: FrameSink + ?Sized>(&self, mask: Option<[u8; 4]>, sink: &mut S) -> usize {
+        let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+        let (head, head_len) = self.header(0, mask_bit);
+        let Some(mask) = mask else {
+            sink.reserve(head_len + self.data.len());
+            sink.extend_from_slice(&head[..head_len]);
+            sink.extend_from_slice(self.data);
+            return self.encoded_len(false);
+        };
+        sink.reserve(head_len + 4 + self.data.len());
+        sink.extend_from_slice(&head[..head_len]);
+        sink.extend_from_slice(&mask);
+        // chunk length is a multiple of the mask period.
+        let mut chunk = [0; 512];
+        for data in self.data.chunks(chunk.len()) {
+            let chunk = &mut chunk[..data.len()];
+            chunk.copy_from_slice(data);
+            unmask(chunk, mask, 0);
+            sink.extend_from_slice(chunk);
+        }
+        self.encoded_len(true)
+    }
+
+    /// Length of the encoded frame, including the masking key if `masked`.
+    #[inline]
+    pub fn encoded_len(&self, masked: bool) -> usize {
+        self.header_len() + if masked { 4 } else { 0 } + self.data.len()
+    }
+
+    /// Length of the frame header, excluding the masking key.
+    #[inline]
+    pub(crate) fn header_len(&self) -> usize {
+        match self.data.len() {
+            0..=125 => 2,
+            126..=65535 => 4,
+            _ => 10,
+        }
+    }
+
+    /// Encodes the frame header into a stack buffer, returns it with its length.
+    #[inline]
+    pub(crate) fn header(&self, rsv: u8, mask_bit: u8) -> ([u8; 10], usize) {
+        let mut head = [0; 10];
+        #[cfg(not(feature = "safe-encode"))]
+        let head_len = unsafe { self.encode_header_unchecked(head.as_mut_ptr(), rsv, mask_bit) };
+        #[cfg(feature = "safe-encode")]
+        let head_len = self.encode_header(&mut head, rsv, mask_bit);
+        (head, head_len)
+    }
+
+    // The reserved lengths can't overflow: a slice is at most `isize::MAX` bytes long,
+    // and every payload length fits in the 64-bit length of the header.
+    #[cfg(not(feature = "safe-encode"))]
+    #[inline]
+    pub(crate) fn write_without_mask(&self, rsv: u8, buf: &mut Vec<u8>) {
+        buf.reserve(self.header_len() + self.data.len());
+        unsafe {
+            let len = buf.len();
+            let dist = buf.as_mut_ptr().add(len);
+            let head_len = self.encode_header_unchecked(dist, rsv, 0);
+            core::ptr::copy_nonoverlapping(self.data.as_ptr(), dist.add(head_len), self.data.len());
+            buf.set_len(len + head_len + self.data.len());
+        }
+    }
+
+    #[cfg(not(feature = "safe-encode"))]
+    #[inline]
+    pub(crate) fn write_with(&self, rsv: u8, mask: [u8; 4], buf: &mut Vec<u8>) {
+        buf.reserve(self.header_len() + 4 + self.data.len());
         unsafe {
-            let dist = buf.as_mut_ptr();
-            let head_len = self.encode_header_unchecked(dist, 0x80);
+            let len = buf.len();
+            let dist = buf.as_mut_ptr().add(len);
+            let head_len = self.encode_header_unchecked(dist, rsv, 0x80);
 
             let [a, b, c, d] = mask;
             dist.add(head_len).write(a);
@@ -33,21 +146,79 @@ impl<'a> Frame<'a> {
             dist.add(head_len + 3).write(d);
 
             let dist = dist.add(head_len + 4);
-            // TODO: Use SIMD wherever possible for best performance
-            for i in 0..self.data.len() {
-                dist.add(i)
-                    .write(self.data.get_unchecked(i) ^ mask.get_unchecked(i & 3));
+            core::ptr::copy_nonoverlapping(self.data.as_ptr(), dist, self.data.len());
+            unmask(
+                core::slice::from_raw_parts_mut(dist, self.data.len()),
+                mask,
+                0,
+            );
+            buf.set_len(len + head_len + 4 + self.data.len());
+        }
+    }
+
+    #[cfg(feature = "safe-encode")]
+    #[inline]
+    pub(crate) fn write_without_mask(&self, rsv: u8, buf: &mut Vec<u8>) {
+        let (head, head_len) = self.header(rsv, 0);
+        buf.reserve(head_len + self.data.len());
+        buf.extend_from_slice(&head[..head_len]);
+        buf.extend_from_slice(self.data);
+    }
+
+    #[cfg(feature = "safe-encode")]
+    #[inline]
+    pub(crate) fn write_with(&self, rsv: u8, mask: [u8; 4], buf: &mut Vec<u8>) {
+        let mut head = [0; 14];
+        let head_len = self.encode_header((&mut head[..10]).try_into().unwrap(), rsv, 0x80);
+        head[head_len..head_len + 4].copy_from_slice(&mask);
+        buf.reserve(head_len + 4 + self.data.len());
+        buf.extend_from_slice(&head[..head_len + 4]);
+        let start = buf.len();
+        buf.extend_from_slice(self.data);
+        unmask(&mut buf[start..], mask, 0);
+    }
+
+    /// Returns the number of bytes written, which is [Frame::header_len].
+    #[cfg(feature = "safe-encode")]
+    #[inline]
+    fn encode_header(&self, head: &mut [u8; 10], rsv: u8, mask_bit: u8) -> usize {
+        debug_assert!(
+            !self.opcode.is_control() || (self.fin && self.data.len() <= 125),
+            "invalid control frame"
+        );
+        head[0] = ((self.fin as u8) << 7) | rsv | self.opcode as u8;
+        let len = self.data.len();
+        match self.header_len() {
+            2 => head[1] = mask_bit | len as u8,
+            4 => {
+                head[1] = mask_bit | 126;
+                head[2..4].copy_from_slice(&(len as u16).to_be_bytes());
+            }
+            _ => {
+                head[1] = mask_bit | 127;
+                head[2..10].copy_from_slice(&(len as u64).to_be_bytes());
             }
-            buf.set_len(head_len + 4 + self.data.len());
         }
-        buf
+        self.header_len()
     }
 
+    /// Returns the number of bytes written, which is [Frame::header_len].
+    ///
     /// # SEAFTY
     ///
-    /// - `dist` must be valid for writes of 10 bytes.
-    pub(crate) unsafe fn encode_header_unchecked(&self, dist: *mut u8, mask_bit: u8) -> usize {
-        dist.write(((self.fin as u8) << 7) | self.opcode);
+    /// - `dist` must be valid for writes of [Frame::header_len] bytes.
+    #[cfg(not(feature = "safe-encode"))]
+    pub(crate) unsafe fn encode_header_unchecked(
+        &self,
+        dist: *mut u8,
+        rsv: u8,
+        mask_bit: u8,
+    ) -> usize {
+        debug_assert!(
+            !self.opcode.is_control() || (self.fin && self.data.len() <= 125),
+            "invalid control frame"
+        );
+        dist.write(((self.fin as u8) << 7) | rsv | self.opcode as u8);
         if self.data.len() < 126 {
             dist.add(1).write(mask_bit | self.data.len() as u8);
             2
@@ -73,12 +244,42 @@ impl<'a> Frame<'a> {
     }
 }
 
+/// Growable buffer that frames can be encoded into, see [Frame::encode_to].
+pub trait FrameSink {
+    /// Reserves capacity for at least `additional` more bytes.
+    fn reserve(&mut self, additional: usize);
+    /// Appends all bytes of `data`.
+    fn extend_from_slice(&mut self, data: &[u8]);
+}
+
+impl FrameSink for Vec<u8> {
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        Vec::reserve(self, additional)
+    }
+    #[inline]
+    fn extend_from_slice(&mut self, data: &[u8]) {
+        Vec::extend_from_slice(self, data)
+    }
+}
+
+impl FrameSink for VecDeque<u8> {
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        VecDeque::reserve(self, additional)
+    }
+    #[inline]
+    fn extend_from_slice(&mut self, data: &[u8]) {
+        self.extend(data)
+    }
+}
+
 impl<'a> From<&'a str> for Frame<'a> {
     #[inline]
     fn from(string: &'a str) -> Self {
         Self {
             fin: true,
-            opcode: 1,
+            opcode: OpCode::Text,
             data: string.as_bytes(),
         }
     }
@@ -89,8 +290,130 @@ impl<'a> From<&'a [u8]> for Frame<'a> {
     fn from(data: &'a [u8]) -> Self {
         Self {
             fin: true,
-            opcode: 2,
+            opcode: OpCode::Binary,
             data,
         }
     }
 }
+
+impl<'a> From<&'a String> for Frame<'a> {
+    #[inline]
+    fn from(string: &'a String) -> Self {
+        Self::from(string.as_str())
+    }
+}
+
+impl<'a> From<&'a Vec<u8>> for Frame<'a> {
+    #[inline]
+    fn from(data: &'a Vec<u8>) -> Self {
+        Self::from(data.as_slice())
+    }
+}
+
+impl<'a> From<&'a Cow<'_, str>> for Frame<'a> {
+    #[inline]
+    fn from(string: &'a Cow<'_, str>) -> Self {
+        Self::from(string.as_ref())
+    }
+}
+
+impl<'a> From<&'a Cow<'_, [u8]>> for Frame<'a> {
+    #[inline]
+    fn from(data: &'a Cow<'_, [u8]>) -> Self {
+        Self::from(data.as_ref())
+    }
+}
+
+/// Header of a frame parsed by [decode].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodedFrame {
+    /// Indicates that this is the final fragment in a message.
+    pub fin: bool,
+    /// [RSV1], [RSV2] and [RSV3] bits, at their header position.
+    ///
+    /// MUST be `0` unless an extension is negotiated that defines meanings for non-zero values.
+    pub rsv: u8,
+    /// Defines the interpretation of the "Payload data".
+    pub opcode: OpCode,
+    /// Masking key, if the payload is masked.
+    pub mask: Option<[u8; 4]>,
+    /// Offset of the payload, which is also the length of the header.
+    pub offset: usize,
+    /// Length of the payload in bytes.
+    pub len: usize,
+}
+
+impl DecodedFrame {
+    /// Returns the (still masked) payload from `buf`, or `None` if `buf` doesn't contain the entire payload yet.
+    #[inline]
+    pub fn payload<'a>(&self, buf: &'a [u8]) -> Option<&'a [u8]> {
+        buf.get(self.offset..self.offset.checked_add(self.len)?)
+    }
+}
+
+/// Parse a frame header from `buf`.
+///
+/// Returns `Ok(None)` if `buf` does not contain the full header yet,
+/// and an error if the opcode is reserved.
+///
+/// ### Example
+///
+/// ```rust
+/// use web_socket::{decode, OpCode};
+///
+/// let buf = [0x81, 5, b'H', b'e', b'l', b'l', b'o'];
+/// let frame = decode(&buf).unwrap().unwrap();
+/// assert_eq!((frame.fin, frame.opcode, frame.mask), (true, OpCode::Text, None));
+/// assert_eq!(frame.payload(&buf), Some(&b"Hello"[..]));
+///
+/// assert_eq!(decode(&buf[..1]), Ok(None));
+/// ```
+pub fn decode(buf: &[u8]) -> Result<Option<DecodedFrame>, ProtocolError> {
+    let [b1, b2, ..] = *buf else {
+        return Ok(None);
+    };
+    let fin = b1 & 0b_1000_0000 != 0;
+    let rsv = b1 & (RSV1 | RSV2 | RSV3);
+    let Some(opcode) = OpCode::from_u8(b1 & 0b_1111) else {
+        return Err(ProtocolError::UnknownOpCode);
+    };
+    let is_masked = b2 & 0b_1000_0000 != 0;
+
+    let (len, mut offset) = match b2 & 0b_111_1111 {
+        126 => match buf.get(2..4) {
+            Some(&[a, b]) => (u16::from_be_bytes([a, b]) as u64, 4),
+            _ => return Ok(None),
+        },
+        127 => match buf.get(2..10) {
+            Some(bytes) => (u64::from_be_bytes(bytes.try_into().unwrap()), 10),
+            None => return Ok(None),
+        },
+        len => (len as u64, 2),
+    };
+    // the most significant bit MUST be 0
+    if len >> 63 != 0 {
+        return Err(ProtocolError::InvalidPayloadLen);
+    }
+    let mask = if is_masked {
+        match buf.get(offset..offset + 4) {
+            Some(&[a, b, c, d]) => {
+                offset += 4;
+                Some([a, b, c, d])
+            }
+            _ => return Ok(None),
+        }
+    } else {
+        None
+    };
+    let Ok(len) = usize::try_from(len) else {
+        return Err(ProtocolError::PayloadTooLarge);
+    };
+    Ok(Some(DecodedFrame {
+        fin,
+        rsv,
+        opcode,
+        mask,
+        offset,
+        len,
+    }))
+}
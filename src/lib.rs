@@ -1,15 +1,41 @@
 #![doc(html_logo_url = "https://cdn.worldvectorlogo.com/logos/websocket.svg")]
 #![doc = include_str!("../README.md")]
 #![warn(missing_docs)]
+#![cfg_attr(feature = "safe-encode", forbid(unsafe_code))]
+#![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+
+#[cfg(feature = "deflate")]
+pub mod deflate;
+mod error;
+#[cfg(feature = "std")]
+pub mod extension;
 mod frame;
+pub mod mask;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "std")]
+pub mod reader;
+#[cfg(feature = "sink")]
+mod sink;
+#[cfg(feature = "stream")]
+pub mod stream;
+pub mod validate;
+#[cfg(feature = "std")]
+pub mod writer;
+#[cfg(feature = "std")]
 mod ws;
+use alloc::{boxed::Box, string::String, vec::Vec};
+pub use error::ProtocolError;
 #[doc(hidden)]
 pub use frame::Frame;
+pub use frame::{decode, DecodedFrame, FrameSink, RSV1, RSV2, RSV3};
+#[cfg(feature = "std")]
 pub use ws::WebSocket;
 
 /// Two roles that can be played by a WebSocket connection: `Server` and `Client`.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Role {
     /// Represent websocket server instance.
     Server,
@@ -17,6 +43,17 @@ pub enum Role {
     Client,
 }
 
+/// State of the closing handshake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// Neither endpoint has sent a Close frame.
+    Open,
+    /// A Close frame was either sent or received (or the connection has failed), but not both.
+    Closing,
+    /// Close frames were both sent and received.
+    Closed,
+}
+
 /// It represent the type of data that is being sent over the WebSocket connection.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MessageType {
@@ -40,10 +77,65 @@ impl MessageType {
     }
 }
 
+/// Defines the interpretation of the "Payload data" of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpCode {
+    /// Denotes a continuation frame of a fragmented message.
+    Continuation = 0,
+    /// Denotes a text frame.
+    Text = 1,
+    /// Denotes a binary frame.
+    Binary = 2,
+    /// Denotes a connection close.
+    Close = 8,
+    /// Denotes a ping.
+    Ping = 9,
+    /// Denotes a pong.
+    Pong = 10,
+}
+
+impl OpCode {
+    /// Returns `None` for opcodes reserved by the protocol: `3-7` and `11-15`.
+    #[inline]
+    pub fn from_u8(opcode: u8) -> Option<Self> {
+        match opcode {
+            0 => Some(OpCode::Continuation),
+            1 => Some(OpCode::Text),
+            2 => Some(OpCode::Binary),
+            8 => Some(OpCode::Close),
+            9 => Some(OpCode::Ping),
+            10 => Some(OpCode::Pong),
+            _ => None,
+        }
+    }
+
+    /// Get the opcode as `u8`
+    #[inline]
+    pub fn as_u8(self) -> u8 {
+        self as u8
+    }
+
+    /// Returns `true` if it is a control frame opcode: `Close`, `Ping` or `Pong`
+    #[inline]
+    pub fn is_control(self) -> bool {
+        self as u8 >= 8
+    }
+}
+
+impl From<MessageType> for OpCode {
+    #[inline]
+    fn from(ty: MessageType) -> Self {
+        match ty {
+            MessageType::Text => OpCode::Text,
+            MessageType::Binary => OpCode::Binary,
+        }
+    }
+}
+
 /// Represents a fragment of a WebSocket message.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Stream {
-    /// Indicates tCopyhe start of a new message fragment of the given [MessageType].
+    /// Indicates the start of a new message fragment of the given [MessageType].
     Start(MessageType),
     /// Indicates the continuation of the current message fragment.
     Next(MessageType),
@@ -64,15 +156,38 @@ impl Stream {
 }
 
 /// Data that is either complete or fragmented.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DataType {
     /// The message is split into fragments, each of which is sent as a separate
-    /// WebSocket message with the [Fragment] variant.
+    /// WebSocket frame, see [Stream].
     Stream(Stream),
     /// A complete WebSocket message in a single transmission.
     Complete(MessageType),
 }
 
+impl DataType {
+    /// Get the [MessageType] of the message, complete or fragmented.
+    #[inline]
+    pub fn ty(&self) -> MessageType {
+        match *self {
+            DataType::Stream(stream) => stream.ty(),
+            DataType::Complete(ty) => ty,
+        }
+    }
+
+    /// Returns `true` if it is the data of a text message
+    #[inline]
+    pub fn is_text(&self) -> bool {
+        self.ty().is_text()
+    }
+
+    /// Returns `true` if it is the data of a binary message
+    #[inline]
+    pub fn is_binary(&self) -> bool {
+        self.ty().is_binary()
+    }
+}
+
 #[derive(Debug)]
 /// Represent a websocket event
 pub enum Event {
@@ -98,59 +213,177 @@ pub enum Event {
     /// A Pong frame MAY be sent unsolicited.  This serves as a unidirectional heartbeat.  A response to an unsolicited Pong frame is not expected.
     Pong(Box<[u8]>),
 
-    /// represents the websocket error message.
-    Error(&'static str),
+    /// represents a violation of the websocket protocol by the endpoint.
+    Error(ProtocolError),
 
     /// represents a successful close event of the WebSocket connection.
     Close {
-        /// represents the status [CloseCode] of the close event.
-        code: u16,
+        /// represents the status [CloseCode] of the close event, `None` if the close frame has no body.
+        code: Option<u16>,
         /// represents the reason for the close event
         reason: Box<str>,
     },
+
+    /// The connection has ended, after an [Event::Close] or an error, nothing more can be received.
+    ///
+    /// Only returned by the managed receive methods, e.g. [WebSocket::recv].
+    Closed,
+}
+
+/// Same as [Event], but the payload borrows the receive buffer of the websocket. Returned by [WebSocket::recv_event_ref].
+#[derive(Debug, Clone)]
+pub enum EventRef<'a> {
+    /// Websocket data frame.
+    Data {
+        /// Represents WebSocket [DataType], Either complete or fragmented
+        ty: DataType,
+        /// Payload, represented as bytes.
+        data: &'a [u8],
+    },
+    /// See [Event::Ping]
+    Ping(&'a [u8]),
+    /// See [Event::Pong]
+    Pong(&'a [u8]),
+    /// See [Event::Error]
+    Error(ProtocolError),
+    /// See [Event::Close]
+    Close {
+        /// represents the status [CloseCode] of the close event, `None` if the close frame has no body.
+        code: Option<u16>,
+        /// represents the reason for the close event
+        reason: &'a str,
+    },
+    /// See [Event::Closed]
+    Closed,
+}
+
+impl From<EventRef<'_>> for Event {
+    fn from(event: EventRef<'_>) -> Self {
+        match event {
+            EventRef::Data { ty, data } => Event::Data {
+                ty,
+                data: data.into(),
+            },
+            EventRef::Ping(data) => Event::Ping(data.into()),
+            EventRef::Pong(data) => Event::Pong(data.into()),
+            EventRef::Error(err) => Event::Error(err),
+            EventRef::Close { code, reason } => Event::Close {
+                code,
+                reason: reason.into(),
+            },
+            EventRef::Closed => Event::Closed,
+        }
+    }
+}
+
+/// A complete WebSocket message, returned by [WebSocket::recv_message].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// A complete text message.
+    Text(String),
+    /// A complete binary message.
+    Binary(Vec<u8>),
+    /// A Ping frame with its "Application data".
+    Ping(Vec<u8>),
+    /// A Pong frame with its "Application data".
+    Pong(Vec<u8>),
+    /// A Close frame with its status code and reason.
+    Close {
+        /// The status [CloseCode], `None` if the close frame has no body.
+        code: Option<u16>,
+        /// The reason for the close event.
+        reason: String,
+    },
+    /// The connection has ended, see [Event::Closed]. It can't be sent.
+    Closed,
 }
 
 /// When closing an established connection an endpoint MAY indicate a reason for closure.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CloseCode {
-    /// The purpose for which the connection was established has been fulfilled
-    Normal = 1000,
-    /// Server going down or a browser having navigated away from a page
-    Away = 1001,
-    /// An endpoint is terminating the connection due to a protocol error.
-    ProtocolError = 1002,
-    /// It has received a type of data it cannot accept
-    Unsupported = 1003,
+    /// `1000`: The purpose for which the connection was established has been fulfilled
+    Normal,
+    /// `1001`: Server going down or a browser having navigated away from a page
+    Away,
+    /// `1002`: An endpoint is terminating the connection due to a protocol error.
+    ProtocolError,
+    /// `1003`: It has received a type of data it cannot accept
+    Unsupported,
 
     // reserved 1004
-    /// MUST NOT be set as a status code in a Close control frame by an endpoint.
+    /// `1005`: MUST NOT be set as a status code in a Close control frame by an endpoint.
     ///
     /// No status code was actually present.
-    NoStatusRcvd = 1005,
-    /// MUST NOT be set as a status code in a Close control frame by an endpoint.
+    NoStatusRcvd,
+    /// `1006`: MUST NOT be set as a status code in a Close control frame by an endpoint.
     ///
     /// Connection was closed abnormally.
-    Abnormal = 1006,
-    /// Application has received data within a message that was not consistent with the type of the message.
-    InvalidPayload = 1007,
-    /// This is a generic status code that can be returned when there is no other more suitable status code.
-    PolicyViolation = 1008,
-    /// Message that is too big for it to process.
-    MessageTooBig = 1009,
-    /// It has expected the server to negotiate one or more extension.
-    MandatoryExt = 1010,
-    /// The server has encountered an unexpected condition that prevented it from fulfilling the request.
-    InternalError = 1011,
-    /// MUST NOT be set as a status code in a Close control frame by an endpoint.
+    Abnormal,
+    /// `1007`: Application has received data within a message that was not consistent with the type of the message.
+    InvalidPayload,
+    /// `1008`: This is a generic status code that can be returned when there is no other more suitable status code.
+    PolicyViolation,
+    /// `1009`: Message that is too big for it to process.
+    MessageTooBig,
+    /// `1010`: It has expected the server to negotiate one or more extension.
+    MandatoryExt,
+    /// `1011`: The server has encountered an unexpected condition that prevented it from fulfilling the request.
+    InternalError,
+    /// `1015`: MUST NOT be set as a status code in a Close control frame by an endpoint.
     ///
     /// The connection was closed due to a failure to perform a TLS handshake.
-    TLSHandshake = 1015,
+    TLSHandshake,
+    /// `3000-3999`: Reserved for use by libraries, frameworks, and applications.
+    Library(u16),
+    /// `4000-4999`: Reserved for private use.
+    Custom(u16),
+}
+
+impl CloseCode {
+    /// Get the status code as `u16`
+    #[inline]
+    pub fn to_u16(self) -> u16 {
+        match self {
+            CloseCode::Normal => 1000,
+            CloseCode::Away => 1001,
+            CloseCode::ProtocolError => 1002,
+            CloseCode::Unsupported => 1003,
+            CloseCode::NoStatusRcvd => 1005,
+            CloseCode::Abnormal => 1006,
+            CloseCode::InvalidPayload => 1007,
+            CloseCode::PolicyViolation => 1008,
+            CloseCode::MessageTooBig => 1009,
+            CloseCode::MandatoryExt => 1010,
+            CloseCode::InternalError => 1011,
+            CloseCode::TLSHandshake => 1015,
+            CloseCode::Library(code) | CloseCode::Custom(code) => code,
+        }
+    }
+
+    /// Returns `None` if the status code is unknown or MUST NOT be set in a Close frame by an endpoint. (`1005`, `1006`, `1015`)
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use web_socket::CloseCode;
+    ///
+    /// assert_eq!(CloseCode::from_u16(1000), Some(CloseCode::Normal));
+    /// assert_eq!(CloseCode::from_u16(4000), Some(CloseCode::Custom(4000)));
+    /// assert_eq!(CloseCode::from_u16(1006), None);
+    /// ```
+    #[inline]
+    pub fn from_u16(code: u16) -> Option<Self> {
+        match code {
+            1000..=1003 | 1007..=1011 | 3000..=4999 => Some(CloseCode::from(code)),
+            _ => None,
+        }
+    }
 }
 
 impl From<CloseCode> for u16 {
     #[inline]
     fn from(code: CloseCode) -> Self {
-        code as u16
+        code.to_u16()
     }
 }
 
@@ -165,10 +398,13 @@ impl From<u16> for CloseCode {
             1005 => CloseCode::NoStatusRcvd,
             1006 => CloseCode::Abnormal,
             1007 => CloseCode::InvalidPayload,
+            1008 => CloseCode::PolicyViolation,
             1009 => CloseCode::MessageTooBig,
             1010 => CloseCode::MandatoryExt,
             1011 => CloseCode::InternalError,
             1015 => CloseCode::TLSHandshake,
+            3000..=3999 => CloseCode::Library(value),
+            4000..=4999 => CloseCode::Custom(value),
             _ => CloseCode::PolicyViolation,
         }
     }
@@ -177,7 +413,7 @@ impl From<u16> for CloseCode {
 impl PartialEq<u16> for CloseCode {
     #[inline]
     fn eq(&self, other: &u16) -> bool {
-        (*self as u16) == *other
+        self.to_u16() == *other
     }
 }
 
@@ -206,12 +442,12 @@ impl CloseReason for u16 {
 impl CloseReason for CloseCode {
     type Bytes = [u8; 2];
     fn to_bytes(self) -> Self::Bytes {
-        (self as u16).to_be_bytes()
+        self.to_u16().to_be_bytes()
     }
 }
 
 impl CloseReason for &str {
-    type Bytes = Vec<u8>;
+    type Bytes = CloseBody;
     fn to_bytes(self) -> Self::Bytes {
         CloseReason::to_bytes((CloseCode::Normal, self))
     }
@@ -222,12 +458,41 @@ where
     Code: Into<u16>,
     Msg: AsRef<[u8]>,
 {
-    type Bytes = Vec<u8>;
+    type Bytes = CloseBody;
     fn to_bytes(self) -> Self::Bytes {
         let (code, reason) = (self.0.into(), self.1.as_ref());
-        let mut data = Vec::with_capacity(2 + reason.len());
-        data.extend_from_slice(&code.to_be_bytes());
-        data.extend_from_slice(reason);
-        data
+        let len = 2 + reason.len();
+        if len > 125 {
+            // rejected when sent, kept whole for the error
+            let mut data = Vec::with_capacity(len);
+            data.extend_from_slice(&code.to_be_bytes());
+            data.extend_from_slice(reason);
+            return CloseBody(CloseBodyRepr::Heap(data));
+        }
+        let mut buf = [0; 125];
+        buf[..2].copy_from_slice(&code.to_be_bytes());
+        buf[2..len].copy_from_slice(reason);
+        CloseBody(CloseBodyRepr::Inline(buf, len as u8))
+    }
+}
+
+/// Body of a Close frame, the status code followed by the reason. See [CloseReason]
+///
+/// The body of a control frame is at most 125 bytes long, so it's stored inline.
+#[derive(Debug, Clone)]
+pub struct CloseBody(CloseBodyRepr);
+
+#[derive(Debug, Clone)]
+enum CloseBodyRepr {
+    Inline([u8; 125], u8),
+    Heap(Vec<u8>),
+}
+
+impl AsRef<[u8]> for CloseBody {
+    fn as_ref(&self) -> &[u8] {
+        match &self.0 {
+            CloseBodyRepr::Inline(buf, len) => &buf[..*len as usize],
+            CloseBodyRepr::Heap(data) => data,
+        }
     }
 }
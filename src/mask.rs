@@ -0,0 +1,105 @@
+//! Masking keys of client frames.
+//!
+//! A client picks a new masking key for every frame it sends, see [Section 5.3](https://datatracker.ietf.org/doc/html/rfc6455#section-5.3).
+//! By default the keys come from [RandMask], a custom source can be set with [WebSocket::with_mask](crate::WebSocket::with_mask).
+//!
+//! ### Example
+//!
+//! ```rust
+//! use web_socket::WebSocket;
+//!
+//! // deterministic masking keys, e.g. for tests.
+//! let mut n = 0_u32;
+//! let ws = WebSocket::client(Vec::<u8>::new()).with_mask(move || {
+//!     n += 1;
+//!     n.to_be_bytes()
+//! });
+//! ```
+
+/// Source of masking keys.
+///
+/// Implemented for any `FnMut() -> [u8; 4]` closure, so a seeded RNG can be wrapped in one.
+pub trait RandKey {
+    /// Returns the masking key of the next frame.
+    fn key(&mut self) -> [u8; 4];
+}
+
+impl<F> RandKey for F
+where
+    F: FnMut() -> [u8; 4],
+{
+    #[inline]
+    fn key(&mut self) -> [u8; 4] {
+        self()
+    }
+}
+
+/// Default masking keys, generated by the thread-local rng of the `rand` crate.
+///
+/// The rng is seeded from the operating system and periodically reseeded,
+/// keys are generated without a system call.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandMask;
+
+#[cfg(feature = "std")]
+impl RandKey for RandMask {
+    #[inline]
+    fn key(&mut self) -> [u8; 4] {
+        rand::random()
+    }
+}
+
+/// Masking keys read directly from the operating system's random number generator, with the `getrandom` crate.
+///
+/// Keys can't be predicted from the state of a userspace rng, for deployments that require it.
+/// The tradeoff is a system call for every frame that is sent, which is noticeably slower than [RandMask].
+///
+/// Panics if the operating system fails to provide random bytes.
+#[cfg(feature = "secure-mask")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SecureMask;
+
+#[cfg(feature = "secure-mask")]
+impl RandKey for SecureMask {
+    #[inline]
+    fn key(&mut self) -> [u8; 4] {
+        let mut key = [0; 4];
+        getrandom::getrandom(&mut key).expect("failed to generate masking key");
+        key
+    }
+}
+
+/// XOR `data` in place with the masking key, as if `data` started at byte `offset` of the payload.
+///
+/// Masking and unmasking are the same operation.
+/// The offset allows unmasking a payload in pieces, e.g. as it's read from the stream.
+///
+/// ### Example
+///
+/// ```rust
+/// use web_socket::mask::unmask;
+///
+/// let key = [0x37, 0xfa, 0x21, 0x3d];
+/// let mut data = [0x7f, 0x9f, 0x4d, 0x51, 0x58];
+/// let (hel, lo) = data.split_at_mut(3);
+/// unmask(hel, key, 0);
+/// unmask(lo, key, 3);
+/// assert_eq!(&data, b"Hello");
+/// ```
+#[inline]
+pub fn unmask(data: &mut [u8], key: [u8; 4], offset: usize) {
+    let mut mask = key;
+    mask.rotate_left(offset % 4);
+    // Words are a multiple of the mask period, so every chunk starts at mask index `0`.
+    let [a, b, c, d] = mask;
+    let mask_u64 = u64::from_ne_bytes([a, b, c, d, a, b, c, d]);
+    let mut chunks = data.chunks_exact_mut(8);
+    for chunk in &mut chunks {
+        let word = u64::from_ne_bytes((&*chunk).try_into().unwrap()) ^ mask_u64;
+        chunk.copy_from_slice(&word.to_ne_bytes());
+    }
+    for (byte, key) in chunks.into_remainder().iter_mut().zip(mask.iter().cycle()) {
+        *byte ^= key;
+    }
+}
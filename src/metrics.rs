@@ -0,0 +1,92 @@
+//! Counters of the frames sent and received by a websocket.
+
+use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+
+/// Counters updated by a [WebSocket](crate::WebSocket), returned by [WebSocket::metrics](crate::WebSocket::metrics).
+///
+/// The counters are atomics, shared by both halves of [WebSocket::split](crate::WebSocket::split).
+/// Lengths are those of the payloads on the wire, compressed if `permessage-deflate` is used.
+///
+/// ### Example
+///
+/// ```rust
+/// use web_socket::WebSocket;
+///
+/// # async {
+/// let mut ws = WebSocket::server(Vec::new());
+/// ws.send("Hello").await?;
+///
+/// let metrics = ws.metrics();
+/// assert_eq!((metrics.frames_sent(), metrics.bytes_sent()), (1, 5));
+/// # std::io::Result::Ok(())
+/// # };
+/// ```
+#[derive(Debug, Default)]
+pub struct Metrics {
+    frames_sent: AtomicU64,
+    bytes_sent: AtomicU64,
+    frames_received: AtomicU64,
+    bytes_received: AtomicU64,
+    pings_received: AtomicU64,
+    pongs_received: AtomicU64,
+    protocol_errors: AtomicU64,
+}
+
+impl Metrics {
+    /// Number of frames sent, including control frames.
+    pub fn frames_sent(&self) -> u64 {
+        self.frames_sent.load(Relaxed)
+    }
+
+    /// Number of payload bytes sent.
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent.load(Relaxed)
+    }
+
+    /// Number of frames received, including control frames.
+    pub fn frames_received(&self) -> u64 {
+        self.frames_received.load(Relaxed)
+    }
+
+    /// Number of payload bytes received.
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received.load(Relaxed)
+    }
+
+    /// Number of Ping frames received.
+    pub fn pings_received(&self) -> u64 {
+        self.pings_received.load(Relaxed)
+    }
+
+    /// Number of Pong frames received.
+    pub fn pongs_received(&self) -> u64 {
+        self.pongs_received.load(Relaxed)
+    }
+
+    /// Number of violations of the protocol by the endpoint, see [crate::ProtocolError].
+    pub fn protocol_errors(&self) -> u64 {
+        self.protocol_errors.load(Relaxed)
+    }
+
+    pub(crate) fn sent(&self, frames: u64, bytes: u64) {
+        self.frames_sent.fetch_add(frames, Relaxed);
+        self.bytes_sent.fetch_add(bytes, Relaxed);
+    }
+
+    pub(crate) fn received(&self, len: usize) {
+        self.frames_received.fetch_add(1, Relaxed);
+        self.bytes_received.fetch_add(len as u64, Relaxed);
+    }
+
+    pub(crate) fn ping(&self) {
+        self.pings_received.fetch_add(1, Relaxed);
+    }
+
+    pub(crate) fn pong(&self) {
+        self.pongs_received.fetch_add(1, Relaxed);
+    }
+
+    pub(crate) fn protocol_error(&self) {
+        self.protocol_errors.fetch_add(1, Relaxed);
+    }
+}
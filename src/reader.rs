@@ -0,0 +1,106 @@
+//! [AsyncRead] over the payload of an incoming message.
+
+use crate::{mask::RandMask, DataType, Event, Stream, WebSocket};
+use std::{
+    future::Future,
+    io::{Error, ErrorKind, Result},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+type Recv<'a, IO, M> =
+    Pin<Box<dyn Future<Output = (&'a mut WebSocket<IO, M>, Result<Event>)> + Send + 'a>>;
+
+/// [AsyncRead] over the payload of the next data message, created with [WebSocket::message_reader].
+///
+/// Fragments are read as they arrive, so the message is never buffered as a whole.
+/// Ping and Pong frames are handled like [WebSocket::recv] does.
+///
+/// Reading reaches EOF at the end of the message, or if a Close frame is received before the message starts.
+/// A Close frame in the middle of the message is an `UnexpectedEof` error, and a protocol violation an `InvalidData` error.
+///
+/// Dropping the reader in the middle of a frame leaves the rest of the frame unread.
+pub struct MessageReader<'a, IO, M = RandMask> {
+    ws: Option<&'a mut WebSocket<IO, M>>,
+    recv: Option<Recv<'a, IO, M>>,
+    data: Box<[u8]>,
+    pos: usize,
+    started: bool,
+    done: bool,
+}
+
+impl<IO, M> WebSocket<IO, M> {
+    /// Returns an [AsyncRead] over the payload of the next data message, e.g. to `tokio::io::copy` a binary message into a file.
+    pub fn message_reader(&mut self) -> MessageReader<'_, IO, M> {
+        MessageReader {
+            ws: Some(self),
+            recv: None,
+            data: Box::default(),
+            pos: 0,
+            started: false,
+            done: false,
+        }
+    }
+}
+
+impl<IO, M> AsyncRead for MessageReader<'_, IO, M>
+where
+    IO: Unpin + AsyncRead + AsyncWrite + Send,
+    M: crate::mask::RandKey + Send,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<()>> {
+        let this = &mut *self;
+        loop {
+            if this.pos < this.data.len() {
+                let len = buf.remaining().min(this.data.len() - this.pos);
+                buf.put_slice(&this.data[this.pos..this.pos + len]);
+                this.pos += len;
+                return Poll::Ready(Ok(()));
+            }
+            if this.done {
+                return Poll::Ready(Ok(()));
+            }
+            let recv = this.recv.get_or_insert_with(|| {
+                let ws = this.ws.take().unwrap();
+                Box::pin(async move {
+                    let event = ws.recv().await;
+                    (ws, event)
+                })
+            });
+            let (ws, event) = ready!(recv.as_mut().poll(cx));
+            this.recv = None;
+            this.ws = Some(ws);
+
+            match event {
+                Ok(Event::Data { ty, data }) => {
+                    this.started = true;
+                    this.done =
+                        matches!(ty, DataType::Complete(_) | DataType::Stream(Stream::End(_)));
+                    this.data = data;
+                    this.pos = 0;
+                }
+                Ok(Event::Ping(_) | Event::Pong(_)) => {}
+                Ok(Event::Close { .. } | Event::Closed) => {
+                    this.done = true;
+                    if this.started {
+                        let err = "close frame in the middle of a message";
+                        return Poll::Ready(Err(Error::new(ErrorKind::UnexpectedEof, err)));
+                    }
+                }
+                Ok(Event::Error(err)) => {
+                    this.done = true;
+                    return Poll::Ready(Err(Error::new(ErrorKind::InvalidData, err)));
+                }
+                Err(err) => {
+                    this.done = true;
+                    return Poll::Ready(Err(err));
+                }
+            }
+        }
+    }
+}
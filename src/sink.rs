@@ -0,0 +1,126 @@
+//! [Sink] of outgoing messages.
+
+use crate::{mask::RandKey, *};
+use futures_sink::Sink;
+use std::{
+    io::{Error, ErrorKind, Result},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio::io::AsyncWrite;
+
+/// [Sink::poll_ready] writes out the buffered frames, once they exceed this many bytes.
+const BUFFER_LEN: usize = 8 * 1024;
+
+impl<IO, M> WebSocket<IO, M>
+where
+    IO: Unpin + AsyncWrite,
+    M: RandKey,
+{
+    fn buffer_message(&mut self, msg: &Message) -> Result<()> {
+        let mut close = Vec::new();
+        let frame = match msg {
+            Message::Text(text) => Frame::from(text),
+            Message::Binary(data) => Frame::from(data),
+            Message::Ping(data) => Frame {
+                fin: true,
+                opcode: OpCode::Ping,
+                data,
+            },
+            Message::Pong(data) => Frame {
+                fin: true,
+                opcode: OpCode::Pong,
+                data,
+            },
+            Message::Close { code, reason } => {
+                match code {
+                    Some(code) => {
+                        close.extend_from_slice(&code.to_be_bytes());
+                        close.extend_from_slice(reason.as_bytes());
+                    }
+                    None if reason.is_empty() => {}
+                    None => {
+                        return Err(Error::new(
+                            ErrorKind::InvalidInput,
+                            "close reason requires a status code",
+                        ))
+                    }
+                }
+                Frame {
+                    fin: true,
+                    opcode: OpCode::Close,
+                    data: &close,
+                }
+            }
+            Message::Closed => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "closed isn't a sendable message",
+                ))
+            }
+        };
+        self.check_frame(&frame)?;
+        let (data, rsv) = self.transform(&frame)?;
+        let data = data.as_deref().unwrap_or(frame.data);
+        self.buffer_frame(&Frame { data, ..frame }, rsv);
+        if let OpCode::Close = frame.opcode {
+            self.close.set_sent();
+        }
+        Ok(())
+    }
+
+    fn poll_write_buf(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        while !self.write_buf.is_empty() {
+            let amt = ready!(Pin::new(&mut self.stream).poll_write(cx, &self.write_buf))?;
+            if amt == 0 {
+                return Poll::Ready(Err(ErrorKind::WriteZero.into()));
+            }
+            self.write_buf.drain(..amt);
+        }
+        self.clear_write_buf(true);
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Sends [Message]s, see [WebSocket::send].
+///
+/// Messages are buffered, and written out by [Sink::poll_flush] (or once the buffer is full).
+/// [Sink::poll_close] sends a Close frame, unless one was already sent, and then shuts down the stream.
+///
+/// [WebSocket::send] takes precedence over `SinkExt::send`, call it as `SinkExt::send(&mut ws, msg)`.
+impl<IO, M> Sink<Message> for WebSocket<IO, M>
+where
+    IO: Unpin + AsyncWrite,
+    M: RandKey + Unpin,
+{
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        if this.write_buf.len() < BUFFER_LEN {
+            return Poll::Ready(Ok(()));
+        }
+        this.poll_write_buf(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, msg: Message) -> Result<()> {
+        self.get_mut().buffer_message(&msg)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_buf(cx))?;
+        Pin::new(&mut this.stream).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        if !self.close.sent() {
+            self.buffer_message(&Message::Close {
+                code: None,
+                reason: String::new(),
+            })?;
+        }
+        ready!(self.as_mut().poll_flush(cx))?;
+        Pin::new(&mut self.get_mut().stream).poll_shutdown(cx)
+    }
+}
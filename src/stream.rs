@@ -0,0 +1,74 @@
+//! [Stream] of incoming messages.
+
+use crate::{mask::RandMask, Message, WebSocket};
+use futures_core::Stream;
+use std::{
+    future::Future,
+    io::Result,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+type Recv<IO, M> = Pin<Box<dyn Future<Output = (WebSocket<IO, M>, Result<Message>)> + Send>>;
+
+/// [Stream] of the messages received by [WebSocket::recv_message], created with [WebSocket::into_stream].
+///
+/// Pings are answered (see [WebSocket::auto_pong]) and a Close frame is echoed, like [WebSocket::recv] does.
+/// The stream ends after [Message::Close] or an error, and if the connection ends between messages.
+pub struct MessageStream<IO, M = RandMask> {
+    ws: Option<WebSocket<IO, M>>,
+    recv: Option<Recv<IO, M>>,
+}
+
+// The websocket is never pinned, it is moved into and out of the boxed future.
+impl<IO, M> Unpin for MessageStream<IO, M> {}
+
+impl<IO, M> WebSocket<IO, M> {
+    /// Converts into a [Stream] of incoming messages.
+    pub fn into_stream(self) -> MessageStream<IO, M> {
+        MessageStream {
+            ws: Some(self),
+            recv: None,
+        }
+    }
+}
+
+impl<IO, M> MessageStream<IO, M> {
+    /// Returns the websocket, or `None` if a message is being received.
+    pub fn into_inner(self) -> Option<WebSocket<IO, M>> {
+        self.ws
+    }
+}
+
+impl<IO, M> Stream for MessageStream<IO, M>
+where
+    IO: Unpin + AsyncRead + AsyncWrite + Send + 'static,
+    M: crate::mask::RandKey + Send + 'static,
+{
+    type Item = Result<Message>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.recv.is_none() {
+            let Some(mut ws) = self.ws.take() else {
+                return Poll::Ready(None);
+            };
+            if ws.close.received() {
+                self.ws = Some(ws);
+                return Poll::Ready(None);
+            }
+            self.recv = Some(Box::pin(async move {
+                let msg = ws.recv_message().await;
+                (ws, msg)
+            }));
+        }
+        let recv = self.recv.as_mut().unwrap();
+        let (ws, msg) = ready!(recv.as_mut().poll(cx));
+        self.recv = None;
+        self.ws = Some(ws);
+        match msg {
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => Poll::Ready(None),
+            msg => Poll::Ready(Some(msg)),
+        }
+    }
+}
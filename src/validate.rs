@@ -0,0 +1,73 @@
+//! Incremental validation of utf-8 encoded text, that may arrive in chunks.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use web_socket::validate::{Utf8Status, Utf8Validator};
+//!
+//! let mut utf8 = Utf8Validator::default();
+//! assert_eq!(utf8.feed(&[b'a', 0xE2]), Utf8Status::Incomplete);
+//! assert_eq!(utf8.feed(&[0x82, 0xAC]), Utf8Status::Valid); // "a€"
+//! assert_eq!(utf8.feed(&[0xFF]), Utf8Status::Invalid);
+//! ```
+
+/// Result of validating a chunk of utf-8 encoded data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Utf8Status {
+    /// All the data received so far is valid utf-8.
+    Valid,
+    /// The data is valid so far, but ends with an incomplete code point.
+    Incomplete,
+    /// The data is not valid utf-8.
+    Invalid,
+}
+
+/// Incremental utf-8 validator. A code point may be split across multiple chunks.
+#[derive(Debug, Default, Clone)]
+pub struct Utf8Validator {
+    partial: [u8; 4],
+    len: u8,
+}
+
+impl Utf8Validator {
+    /// Validate next chunk of data.
+    pub fn feed(&mut self, mut data: &[u8]) -> Utf8Status {
+        if self.len > 0 {
+            let len = self.len as usize;
+            let take = (char_width(self.partial[0]) - len).min(data.len());
+            self.partial[len..len + take].copy_from_slice(&data[..take]);
+            self.len += take as u8;
+            data = &data[take..];
+
+            match core::str::from_utf8(&self.partial[..self.len as usize]) {
+                Ok(_) => self.len = 0,
+                Err(err) if err.error_len().is_some() => return Utf8Status::Invalid,
+                Err(_) => return Utf8Status::Incomplete,
+            }
+        }
+        match core::str::from_utf8(data) {
+            Ok(_) => Utf8Status::Valid,
+            Err(err) if err.error_len().is_some() => Utf8Status::Invalid,
+            Err(err) => {
+                let rest = &data[err.valid_up_to()..];
+                self.partial[..rest.len()].copy_from_slice(rest);
+                self.len = rest.len() as u8;
+                Utf8Status::Incomplete
+            }
+        }
+    }
+
+    /// Clear the state, so that it can be reused for a new message.
+    pub fn reset(&mut self) {
+        self.len = 0;
+    }
+}
+
+/// Width of an utf-8 encoded code point, given a valid leading byte.
+fn char_width(byte: u8) -> usize {
+    match byte {
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        _ => 4,
+    }
+}
@@ -0,0 +1,98 @@
+//! Send a message incrementally, as its payload is produced.
+
+use crate::{mask::RandKey, mask::RandMask, Frame, MessageType, OpCode, WebSocket};
+use std::io::Result;
+use tokio::io::AsyncWrite;
+
+/// Sends a message in fragments of `fragment_size` bytes, created with [WebSocket::start_message].
+///
+/// The payload is buffered until a fragment is full, so the whole message is never buffered at once.
+/// The last fragment is sent by [MessageWriter::finish], messages are sent uncompressed.
+///
+/// Other frames can't be sent until the message is finished, dropping the writer before [MessageWriter::finish]
+/// leaves the message unfinished, and the endpoint fails the connection on the next data frame.
+pub struct MessageWriter<'a, IO, M = RandMask> {
+    ws: &'a mut WebSocket<IO, M>,
+    opcode: OpCode,
+    fragment_size: usize,
+    buf: Vec<u8>,
+}
+
+impl<IO, M> WebSocket<IO, M> {
+    /// Starts a message of type `ty`, sent in fragments of `fragment_size` bytes.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// use web_socket::{MessageType, WebSocket};
+    ///
+    /// # async {
+    /// let mut ws = WebSocket::client(Vec::new());
+    /// let mut message = ws.start_message(MessageType::Text, 4096);
+    /// for n in 0..1000 {
+    ///     message.write(format!("{n}, ").as_bytes()).await?;
+    /// }
+    /// message.finish().await?;
+    /// # std::io::Result::Ok(())
+    /// # };
+    /// ```
+    ///
+    /// Panics if `fragment_size` is `0`.
+    pub fn start_message(
+        &mut self,
+        ty: MessageType,
+        fragment_size: usize,
+    ) -> MessageWriter<'_, IO, M> {
+        assert!(fragment_size > 0, "fragment size must not be `0`");
+        MessageWriter {
+            ws: self,
+            opcode: ty.into(),
+            fragment_size,
+            buf: Vec::new(),
+        }
+    }
+}
+
+impl<IO, M> MessageWriter<'_, IO, M>
+where
+    IO: Unpin + AsyncWrite,
+    M: RandKey,
+{
+    /// Appends `data` to the message, full fragments are sent.
+    pub async fn write(&mut self, mut data: &[u8]) -> Result<()> {
+        // A full fragment is kept until more data is written, so `finish` has a payload to send with the `FIN` bit.
+        while self.buf.len() + data.len() > self.fragment_size {
+            let (fragment, rest) = data.split_at(self.fragment_size - self.buf.len());
+            let frame = match self.buf.is_empty() {
+                true => fragment,
+                false => {
+                    self.buf.extend_from_slice(fragment);
+                    &self.buf
+                }
+            };
+            self.ws
+                .send_raw(Frame {
+                    fin: false,
+                    opcode: self.opcode,
+                    data: frame,
+                })
+                .await?;
+            self.opcode = OpCode::Continuation;
+            self.buf.clear();
+            data = rest;
+        }
+        self.buf.extend_from_slice(data);
+        Ok(())
+    }
+
+    /// Sends the last fragment of the message.
+    pub async fn finish(self) -> Result<()> {
+        self.ws
+            .send_raw(Frame {
+                fin: true,
+                opcode: self.opcode,
+                data: &self.buf,
+            })
+            .await
+    }
+}
@@ -1,89 +1,494 @@
 #![allow(clippy::unusual_byte_groupings)]
-use crate::*;
-use std::io::{IoSlice, Result};
-use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use crate::{
+    extension::{Extension, IncomingExtensions, OutgoingExtensions},
+    mask::*,
+    validate::*,
+    *,
+};
+use std::{
+    io::{IoSlice, Result},
+    sync::{
+        atomic::{AtomicBool, Ordering::Relaxed},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadHalf, WriteHalf};
+
+macro_rules! io_err {
+    [$kind: ident, $msg: expr] => {
+        return Err(std::io::Error::new(std::io::ErrorKind::$kind, $msg))
+    };
+}
 
 /// WebSocket implementation for both client and server
+///
+/// `Mask` is the source of masking keys for client frames, see [crate::mask].
 #[derive(Debug)]
-pub struct WebSocket<Stream> {
+pub struct WebSocket<Stream, Mask = RandMask> {
     /// it is a low-level abstraction that represents the underlying byte stream over which WebSocket messages are exchanged.
     pub stream: Stream,
 
-    /// Maximum allowed payload length in bytes.
+    /// Maximum allowed payload length in bytes, of a single frame.
+    ///
+    /// The length is checked as soon as the frame header is read, before the payload.
+    /// Exceeding it is an [Event::Error], which can be answered with [CloseCode::MessageTooBig](crate::CloseCode::MessageTooBig).
     ///
     /// Default: 16 MB
     pub max_payload_len: usize,
 
+    /// Maximum allowed length in bytes of a fragmented message, the sum of all its fragments.
+    ///
+    /// Exceeding it is an [Event::Error], which can be answered with [CloseCode::MessageTooBig](crate::CloseCode::MessageTooBig).
+    ///
+    /// Default: 64 MB
+    pub max_message_len: usize,
+
+    /// Respond to Ping frames with a Pong frame in [WebSocket::recv].
+    ///
+    /// Default: `true`
+    pub auto_pong: bool,
+
+    /// Send a Ping frame from [WebSocket::recv], when nothing is received for this long.
+    ///
+    /// If nothing is received within another interval, the connection is considered dead
+    /// and [WebSocket::recv] returns a `TimedOut` error. The Pong frame sent in response is returned as [Event::Pong].
+    ///
+    /// Uses the timer of the tokio runtime, which must be enabled (e.g. with `Builder::enable_time`).
+    ///
+    /// Default: `None`
+    pub keepalive: Option<Duration>,
+
+    /// Messages smaller than this many bytes are sent without compression,
+    /// when `permessage-deflate` is enabled. They aren't transformed by other extensions either.
+    ///
+    /// Default: `0`
+    #[cfg(feature = "deflate")]
+    pub compress_min_size: usize,
+
     role: Role,
-    is_closed: bool,
+    pub(crate) close: Arc<CloseState>,
     fragment: Option<MessageType>,
+    /// Reserved bits of the first frame of the message that is being received.
+    message_rsv: u8,
+    message_len: usize,
+    utf8: Utf8Validator,
+    message: Vec<u8>,
+    read_buf: Vec<u8>,
+    pub(crate) write_buf: Vec<u8>,
+    mask: Mask,
+    #[cfg(feature = "metrics")]
+    metrics: std::sync::Arc<crate::metrics::Metrics>,
+    /// Frames and bytes in `write_buf`, counted as sent once they are written.
+    #[cfg(feature = "metrics")]
+    pub(crate) buffered: (u64, u64),
+    outgoing: OutgoingExtensions,
+    incoming: IncomingExtensions,
+    /// Reserved bits defined by the extensions.
+    extension_rsv: u8,
+}
+
+/// Progress of the closing handshake, shared by both halves of [WebSocket::split].
+#[derive(Debug, Default)]
+pub(crate) struct CloseState {
+    sent: AtomicBool,
+    received: AtomicBool,
+}
+
+impl CloseState {
+    pub fn sent(&self) -> bool {
+        self.sent.load(Relaxed)
+    }
+
+    pub fn received(&self) -> bool {
+        self.received.load(Relaxed)
+    }
+
+    pub fn set_sent(&self) {
+        self.sent.store(true, Relaxed);
+    }
+
+    pub fn set_received(&self) {
+        self.received.store(true, Relaxed);
+    }
 }
 
 impl<IO> WebSocket<IO> {
-    /// Create a new websocket client instance.
+    /// Create a new websocket client instance, over an already upgraded `stream`. See [WebSocket::server].
     #[inline]
     pub fn client(stream: IO) -> Self {
         Self::from((stream, Role::Client))
     }
     /// Create a websocket server instance.
+    ///
+    /// The handshake isn't performed, `stream` must be an already upgraded connection (e.g. from `hyper::upgrade::on`)
+    /// and the caller is responsible for having validated the handshake. The websocket starts in the [State::Open] state.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// use web_socket::WebSocket;
+    /// # async fn upgraded() -> tokio::io::DuplexStream { unimplemented!() }
+    ///
+    /// # async {
+    /// // e.g. a connection upgraded by an http server.
+    /// let stream = upgraded().await;
+    /// let ws = WebSocket::server(stream);
+    /// # };
+    /// ```
     #[inline]
     pub fn server(stream: IO) -> Self {
         Self::from((stream, Role::Server))
     }
 }
 
-impl<W> WebSocket<W>
+impl<IO, M> WebSocket<IO, M> {
+    /// Returns the state of the closing handshake.
+    pub fn state(&self) -> State {
+        match (self.close.sent(), self.close.received()) {
+            (false, false) => State::Open,
+            (true, true) => State::Closed,
+            _ => State::Closing,
+        }
+    }
+
+    /// Returns the counters of this websocket, shared with the other half after [WebSocket::split].
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> &std::sync::Arc<crate::metrics::Metrics> {
+        &self.metrics
+    }
+
+    /// Use `mask` as the source of masking keys.
+    pub fn with_mask<Mask: RandKey>(self, mask: Mask) -> WebSocket<IO, Mask> {
+        WebSocket {
+            stream: self.stream,
+            max_payload_len: self.max_payload_len,
+            max_message_len: self.max_message_len,
+            auto_pong: self.auto_pong,
+            keepalive: self.keepalive,
+            #[cfg(feature = "deflate")]
+            compress_min_size: self.compress_min_size,
+            role: self.role,
+            close: self.close,
+            fragment: self.fragment,
+            message_rsv: self.message_rsv,
+            message_len: self.message_len,
+            utf8: self.utf8,
+            message: self.message,
+            read_buf: self.read_buf,
+            write_buf: self.write_buf,
+            mask,
+            #[cfg(feature = "metrics")]
+            metrics: self.metrics,
+            #[cfg(feature = "metrics")]
+            buffered: self.buffered,
+            outgoing: self.outgoing,
+            incoming: self.incoming,
+            extension_rsv: self.extension_rsv,
+        }
+    }
+
+    /// Splits the websocket into a read half and a write half, which can be used concurrently (e.g. from different tasks).
+    ///
+    /// The read half receives frames with [WebSocket::recv_event]. It can't respond to Ping or Close frames,
+    /// that's up to the write half. Both halves share the state of the closing handshake:
+    /// once the read half has received a Close frame, the write half refuses to send data frames.
+    ///
+    /// The read half keeps the incoming state of the extensions and the write half the outgoing one,
+    /// see [Extension::split].
+    pub fn split(self) -> (WebSocket<ReadHalf<IO>>, WebSocket<WriteHalf<IO>, M>)
+    where
+        IO: AsyncRead + AsyncWrite,
+    {
+        let (reader, writer) = tokio::io::split(self.stream);
+        let read = WebSocket {
+            stream: reader,
+            max_payload_len: self.max_payload_len,
+            max_message_len: self.max_message_len,
+            auto_pong: self.auto_pong,
+            keepalive: self.keepalive,
+            #[cfg(feature = "deflate")]
+            compress_min_size: self.compress_min_size,
+            role: self.role,
+            close: self.close.clone(),
+            fragment: self.fragment,
+            message_rsv: self.message_rsv,
+            message_len: self.message_len,
+            utf8: self.utf8,
+            message: self.message,
+            read_buf: self.read_buf,
+            write_buf: Vec::new(),
+            mask: RandMask,
+            #[cfg(feature = "metrics")]
+            metrics: self.metrics.clone(),
+            #[cfg(feature = "metrics")]
+            buffered: (0, 0),
+            outgoing: OutgoingExtensions::default(),
+            incoming: self.incoming,
+            extension_rsv: self.extension_rsv,
+        };
+        let write = WebSocket {
+            stream: writer,
+            max_payload_len: self.max_payload_len,
+            max_message_len: self.max_message_len,
+            auto_pong: self.auto_pong,
+            keepalive: self.keepalive,
+            #[cfg(feature = "deflate")]
+            compress_min_size: self.compress_min_size,
+            role: self.role,
+            close: self.close,
+            fragment: None,
+            message_rsv: 0,
+            message_len: 0,
+            utf8: Utf8Validator::default(),
+            message: Vec::new(),
+            read_buf: Vec::new(),
+            write_buf: self.write_buf,
+            mask: self.mask,
+            #[cfg(feature = "metrics")]
+            metrics: self.metrics,
+            #[cfg(feature = "metrics")]
+            buffered: self.buffered,
+            outgoing: self.outgoing,
+            incoming: IncomingExtensions::default(),
+            extension_rsv: self.extension_rsv,
+        };
+        (read, write)
+    }
+
+    /// Enable the `permessage-deflate` extension, with the parameters negotiated during the handshake.
+    ///
+    /// Panics if another extension uses the `RSV1` bit, see [WebSocket::add_extension].
+    #[cfg(feature = "deflate")]
+    pub fn enable_deflate(&mut self, config: crate::deflate::DeflateConfig) {
+        self.add_extension(crate::deflate::PerMessageDeflate::new(config, self.role));
+    }
+
+    /// Add an extension negotiated during the handshake, after those that were already added.
+    ///
+    /// Extensions must be added before [WebSocket::split], which moves their states to either half.
+    ///
+    /// Panics if another extension uses the same reserved bits.
+    pub fn add_extension(&mut self, extension: impl Extension) {
+        let rsv = extension.rsv();
+        assert_eq!(
+            self.extension_rsv & rsv,
+            0,
+            "reserved bits are used by another extension"
+        );
+        self.extension_rsv |= rsv;
+        let (outgoing, incoming) = extension.split();
+        self.outgoing.push(outgoing);
+        self.incoming.push(incoming);
+    }
+}
+
+impl<W, M> WebSocket<W, M>
 where
     W: Unpin + AsyncWrite,
+    M: RandKey,
 {
     #[doc(hidden)]
     pub async fn send_raw(&mut self, frame: Frame<'_>) -> Result<()> {
-        let buf = match self.role {
-            Role::Server => {
-                if self.stream.is_write_vectored() {
-                    let mut head = [0; 10];
-                    let head_len = unsafe { frame.encode_header_unchecked(head.as_mut_ptr(), 0) };
-                    let total_len = head_len + frame.data.len();
-
-                    let mut bufs = [IoSlice::new(&head[..head_len]), IoSlice::new(frame.data)];
-                    let mut amt = self.stream.write_vectored(&bufs).await?;
-                    if amt == total_len {
-                        return Ok(());
-                    }
-                    while amt < head_len {
-                        bufs[0] = IoSlice::new(&head[amt..head_len]);
-                        amt += self.stream.write_vectored(&bufs).await?;
-                    }
-                    if amt < total_len {
-                        self.stream.write_all(&frame.data[amt - head_len..]).await?;
+        self.send_frame(frame, 0).await
+    }
+
+    /// Checks that `frame` may be sent.
+    pub(crate) fn check_frame(&self, frame: &Frame) -> Result<()> {
+        if self.close.sent() {
+            io_err!(NotConnected, "send after close");
+        }
+        if self.close.received() && !frame.opcode.is_control() {
+            io_err!(NotConnected, "send after close received");
+        }
+        if frame.opcode.is_control() {
+            if !frame.fin {
+                io_err!(InvalidInput, "control frame must not be fragmented");
+            }
+            if frame.data.len() > 125 {
+                io_err!(
+                    InvalidInput,
+                    "control frame must have a payload length of 125 bytes or less"
+                );
+            }
+            if let OpCode::Close = frame.opcode {
+                match frame.data {
+                    [] => {}
+                    [a, b, reason @ ..]
+                        if CloseCode::from_u16(u16::from_be_bytes([*a, *b])).is_some() =>
+                    {
+                        if std::str::from_utf8(reason).is_err() {
+                            io_err!(InvalidInput, "close reason must be valid utf-8");
+                        }
                     }
-                    return Ok(());
+                    _ => io_err!(InvalidInput, "invalid close code"),
                 }
-                frame.encode_without_mask()
             }
-            Role::Client => frame.encode_with(rand::random::<u32>().to_ne_bytes()),
-        };
-        self.stream.write_all(&buf).await
+        }
+        Ok(())
+    }
+
+    /// Appends the encoded frame to the write buffer.
+    pub(crate) fn buffer_frame(&mut self, frame: &Frame, rsv: u8) {
+        #[cfg(feature = "metrics")]
+        {
+            self.buffered.0 += 1;
+            self.buffered.1 += frame.data.len() as u64;
+        }
+        #[cfg(feature = "tracing")]
+        trace_sent(frame, rsv);
+        match self.role {
+            Role::Server => frame.write_without_mask(rsv, &mut self.write_buf),
+            Role::Client => {
+                let mask = self.mask.key();
+                frame.write_with(rsv, mask, &mut self.write_buf)
+            }
+        }
+    }
+
+    /// Returns the payload of `frame` transformed by the extensions, if it's a complete message,
+    /// with the reserved bits of its first frame.
+    pub(crate) fn transform(&mut self, frame: &Frame) -> Result<(Option<Vec<u8>>, u8)> {
+        if !frame.fin || !matches!(frame.opcode, OpCode::Text | OpCode::Binary) {
+            return Ok((None, 0));
+        }
+        #[cfg(feature = "deflate")]
+        if frame.data.len() < self.compress_min_size {
+            return Ok((None, 0));
+        }
+        self.outgoing.transform(frame.data)
+    }
+
+    async fn send_frame(&mut self, frame: Frame<'_>, rsv: u8) -> Result<()> {
+        self.check_frame(&frame)?;
+        // Frames that are still buffered by the `Sink` implementation.
+        if !self.write_buf.is_empty() {
+            self.stream.write_all(&self.write_buf).await?;
+            self.clear_write_buf(true);
+        }
+        if let (Role::Server, true) = (self.role, self.stream.is_write_vectored()) {
+            #[cfg(feature = "tracing")]
+            trace_sent(&frame, rsv);
+            let (head, head_len) = frame.header(rsv, 0);
+            let total_len = head_len + frame.data.len();
+
+            let mut bufs = [IoSlice::new(&head[..head_len]), IoSlice::new(frame.data)];
+            let mut amt = 0;
+            while amt < head_len {
+                bufs[0] = IoSlice::new(&head[amt..head_len]);
+                match self.stream.write_vectored(&bufs).await? {
+                    0 => io_err!(WriteZero, "failed to write whole frame"),
+                    n => amt += n,
+                }
+            }
+            if amt < total_len {
+                self.stream.write_all(&frame.data[amt - head_len..]).await?;
+            }
+            #[cfg(feature = "metrics")]
+            self.metrics.sent(1, frame.data.len() as u64);
+            return Ok(());
+        }
+        self.buffer_frame(&frame, rsv);
+        let res = self.stream.write_all(&self.write_buf).await;
+        self.clear_write_buf(res.is_ok());
+        res
+    }
+
+    /// Clears the write buffer after writing it, its frames are counted as sent if they were `written`.
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    pub(crate) fn clear_write_buf(&mut self, written: bool) {
+        self.write_buf.clear();
+        #[cfg(feature = "metrics")]
+        {
+            let (frames, bytes) = std::mem::take(&mut self.buffered);
+            if written {
+                self.metrics.sent(frames, bytes);
+            }
+        }
     }
 
     /// Send message to a endpoint.
+    ///
+    /// Returns a `NotConnected` error after a Close frame was sent, and for data frames after a Close frame was received.
+    ///
+    /// If `permessage-deflate` is enabled, complete (unfragmented) messages of at least
+    /// `compress_min_size` bytes are compressed. Other extensions transform them too, see [crate::extension].
     pub async fn send(&mut self, data: impl Into<Frame<'_>>) -> Result<()> {
+        let frame = data.into();
+        let (data, rsv) = self.transform(&frame)?;
+        let data = data.as_deref().unwrap_or(frame.data);
+        self.send_frame(Frame { data, ..frame }, rsv).await
+    }
+
+    /// Send message to a endpoint, without compression (or any other extension).
+    ///
+    /// Useful for payloads that are already compressed. Compressed messages are still received as usual.
+    #[cfg(feature = "deflate")]
+    pub async fn send_uncompressed(&mut self, data: impl Into<Frame<'_>>) -> Result<()> {
         self.send_raw(data.into()).await
     }
 
+    /// Send a message split into frames of at most `fragment_size` bytes of payload.
+    ///
+    /// The first frame has the opcode of the message, the following are continuation frames and the last has the `FIN` bit set.
+    /// An empty message is sent as a single frame. If `permessage-deflate` is enabled, the message is compressed as a whole, like with [WebSocket::send].
+    ///
+    /// To send control frames between the fragments, send each fragment with [WebSocket::send] instead.
+    ///
+    /// Returns an `InvalidInput` error if `data` isn't a complete text or binary message, or `fragment_size` is `0`.
+    pub async fn send_fragmented(
+        &mut self,
+        data: impl Into<Frame<'_>>,
+        fragment_size: usize,
+    ) -> Result<()> {
+        let frame = data.into();
+        if !frame.fin || !matches!(frame.opcode, OpCode::Text | OpCode::Binary) {
+            io_err!(InvalidInput, "expected a complete text or binary message");
+        }
+        if fragment_size == 0 {
+            io_err!(InvalidInput, "fragment size must not be `0`");
+        }
+        let (transformed, mut rsv) = self.transform(&frame)?;
+        let data = transformed.as_deref().unwrap_or(frame.data);
+
+        let mut opcode = frame.opcode;
+        let mut fragments = data.chunks(fragment_size).peekable();
+        loop {
+            let data = fragments.next().unwrap_or_default();
+            let fin = fragments.peek().is_none();
+            self.send_frame(Frame { fin, opcode, data }, rsv).await?;
+            if fin {
+                return Ok(());
+            }
+            opcode = OpCode::Continuation;
+            rsv = 0;
+        }
+    }
+
+    /// Send a Close frame, without waiting for the response of the endpoint. See [WebSocket::close].
+    ///
     /// - The Close frame MAY contain a body that indicates a reason for closing.
-    pub async fn close<T>(mut self, reason: T) -> Result<()>
+    ///
+    /// Returns an `InvalidInput` error if the status code must not be sent by an endpoint (e.g. `1005`, `1006`, `1015`),
+    /// or the reason is longer than 123 bytes.
+    pub async fn send_close<T>(&mut self, reason: T) -> Result<()>
     where
         T: CloseReason,
         T::Bytes: AsRef<[u8]>,
     {
+        let data = reason.to_bytes();
+        let data = data.as_ref();
         self.send_raw(Frame {
             fin: true,
-            opcode: 8,
-            data: reason.to_bytes().as_ref(),
+            opcode: OpCode::Close,
+            data,
         })
         .await?;
-        self.stream.flush().await
+        self.stream.flush().await?;
+        self.close.set_sent();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(code = ?data.first_chunk().map(|code| u16::from_be_bytes(*code)), "close frame sent");
+        Ok(())
     }
 
     /// A Ping frame may serve either as a keepalive or as a means to verify that the remote endpoint is still responsive.
@@ -103,7 +508,7 @@ where
     pub async fn send_ping(&mut self, data: impl AsRef<[u8]>) -> Result<()> {
         self.send_raw(Frame {
             fin: true,
-            opcode: 9,
+            opcode: OpCode::Ping,
             data: data.as_ref(),
         })
         .await
@@ -116,21 +521,88 @@ where
     pub async fn send_pong(&mut self, data: impl AsRef<[u8]>) -> Result<()> {
         self.send_raw(Frame {
             fin: true,
-            opcode: 10,
+            opcode: OpCode::Pong,
             data: data.as_ref(),
         })
         .await
     }
 
+    /// Send several frames with a single write.
+    ///
+    /// [WebSocket::send] writes every frame separately, batching saves a write (usually a system call) per frame,
+    /// e.g. when pushing many small messages. The tradeoff is latency: nothing is written until every frame is encoded,
+    /// and the stream isn't flushed, see [WebSocket::flush].
+    ///
+    /// Frames are checked and compressed like with [WebSocket::send], nothing is sent if one of them is invalid.
+    /// A Close frame followed by other frames is an `InvalidInput` error.
+    pub async fn send_all<'a, F>(&mut self, frames: impl IntoIterator<Item = F>) -> Result<()>
+    where
+        F: Into<Frame<'a>>,
+    {
+        let frames: Vec<Frame> = frames.into_iter().map(Into::into).collect();
+        for (i, frame) in frames.iter().enumerate() {
+            self.check_frame(frame)?;
+            if frame.opcode == OpCode::Close && i + 1 < frames.len() {
+                io_err!(InvalidInput, "close frame must be last in batch");
+            }
+        }
+        // Frames that are still buffered by the `Sink` implementation are written first.
+        let buffered = self.write_buf.len();
+        #[cfg(feature = "metrics")]
+        let buffered_metrics = self.buffered;
+        for frame in &frames {
+            let (data, rsv) = match self.transform(frame) {
+                Ok(transformed) => transformed,
+                Err(err) => {
+                    self.write_buf.truncate(buffered);
+                    #[cfg(feature = "metrics")]
+                    {
+                        self.buffered = buffered_metrics;
+                    }
+                    return Err(err);
+                }
+            };
+            let data = data.as_deref().unwrap_or(frame.data);
+            self.buffer_frame(&Frame { data, ..*frame }, rsv);
+        }
+        let res = self.stream.write_all(&self.write_buf).await;
+        self.clear_write_buf(res.is_ok());
+        res?;
+        if let Some(Frame {
+            opcode: OpCode::Close,
+            ..
+        }) = frames.last()
+        {
+            self.close.set_sent();
+        }
+        Ok(())
+    }
+
     /// Flushes this output stream, ensuring that all intermediately buffered contents reach their destination.
-    pub async fn flash(&mut self) -> Result<()> {
+    pub async fn flush(&mut self) -> Result<()> {
         self.stream.flush().await
     }
+
+    #[doc(hidden)]
+    #[deprecated(note = "renamed to `flush`")]
+    pub async fn flash(&mut self) -> Result<()> {
+        self.flush().await
+    }
 }
 
 // ------------------------------------------------------------------------
 
-macro_rules! err { [$msg: expr] => { return Ok(Event::Error($msg)) }; }
+macro_rules! err { [$msg: expr] => { return Ok(Head::Error($msg)) }; }
+
+/// Frame read by [WebSocket::read_frame], its payload is in `read_buf`.
+#[derive(Debug, Clone, Copy)]
+enum Head {
+    Data(DataType),
+    Ping,
+    Pong,
+    Close(Option<u16>),
+    Error(ProtocolError),
+}
 
 #[inline]
 pub async fn read_buf<const N: usize, R>(stream: &mut R) -> Result<[u8; N]>
@@ -142,25 +614,10 @@ where
     Ok(buf)
 }
 
-impl<R> WebSocket<R>
+impl<R, M> WebSocket<R, M>
 where
     R: Unpin + AsyncRead,
 {
-    /// reads [Event] from websocket stream.
-    pub async fn recv(&mut self) -> Result<Event> {
-        if self.is_closed {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::NotConnected,
-                "read after close",
-            ));
-        }
-        let event = self.recv_event().await;
-        if let Ok(Event::Close { .. } | Event::Error(..)) | Err(..) = event {
-            self.is_closed = true;
-        }
-        event
-    }
-
     // ### WebSocket Frame Header
     //
     // ```txt
@@ -185,8 +642,122 @@ where
     // ```
     /// reads [Event] from websocket stream.
     pub async fn recv_event(&mut self) -> Result<Event> {
-        let [b1, b2] = read_buf(&mut self.stream).await?;
+        let head = self.recv_head().await?;
+        Ok(self.take_event(head))
+    }
+
+    /// Same as [WebSocket::recv_event], but the payload borrows the receive buffer of the websocket,
+    /// instead of being allocated for every frame.
+    ///
+    /// The buffer is reused by the next call, so the returned [EventRef] must be dropped before receiving again.
+    /// The buffer keeps the capacity of the largest payload received.
+    pub async fn recv_event_ref(&mut self) -> Result<EventRef<'_>> {
+        let head = self.recv_head().await?;
+        Ok(self.borrow_event(head))
+    }
+
+    async fn recv_head(&mut self) -> Result<Head> {
+        let mut head = [0; 2];
+        let amt = self.stream.read(&mut head).await?;
+        self.recv_frame(head, amt).await
+    }
+
+    /// Reads the rest of the frame, of which `amt` bytes of the header were read.
+    ///
+    /// The end of the stream between messages is an `UnexpectedEof` error,
+    /// and a `ConnectionAborted` error in the middle of a frame or a fragmented message.
+    async fn recv_frame(&mut self, mut head: [u8; 2], amt: usize) -> Result<Head> {
+        if amt == 0 {
+            match self.fragment {
+                None => io_err!(UnexpectedEof, "connection closed"),
+                Some(_) => io_err!(
+                    ConnectionAborted,
+                    "connection closed in the middle of a message"
+                ),
+            }
+        }
+        let res = async {
+            self.stream.read_exact(&mut head[amt..]).await?;
+            self.read_frame(head).await
+        };
+        let head = match res.await {
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => io_err!(
+                ConnectionAborted,
+                "connection closed in the middle of a frame"
+            ),
+            res => res?,
+        };
+        #[cfg(feature = "metrics")]
+        self.count(head);
+        #[cfg(feature = "tracing")]
+        self.trace(head);
+        Ok(head)
+    }
+
+    #[cfg(feature = "tracing")]
+    fn trace(&self, head: Head) {
+        let len = self.read_buf.len();
+        match head {
+            Head::Close(code) => tracing::debug!(?code, "close frame received"),
+            Head::Error(err) => tracing::debug!(%err, "protocol error"),
+            head => tracing::trace!(?head, len, "frame received"),
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    fn count(&self, head: Head) {
+        match head {
+            Head::Ping => self.metrics.ping(),
+            Head::Pong => self.metrics.pong(),
+            Head::Error(_) => self.metrics.protocol_error(),
+            _ => {}
+        }
+    }
+
+    fn borrow_event(&self, head: Head) -> EventRef<'_> {
+        match head {
+            Head::Data(ty) => EventRef::Data {
+                ty,
+                data: &self.read_buf,
+            },
+            Head::Ping => EventRef::Ping(&self.read_buf),
+            Head::Pong => EventRef::Pong(&self.read_buf),
+            Head::Close(code) => EventRef::Close {
+                code,
+                reason: close_reason(&self.read_buf),
+            },
+            Head::Error(err) => EventRef::Error(err),
+        }
+    }
+
+    /// Moves the payload out of `read_buf`, without copying it.
+    fn take_event(&mut self, head: Head) -> Event {
+        let mut data = || std::mem::take(&mut self.read_buf).into_boxed_slice();
+        match head {
+            Head::Data(ty) => Event::Data { ty, data: data() },
+            Head::Ping => Event::Ping(data()),
+            Head::Pong => Event::Pong(data()),
+            Head::Close(code) => Event::Close {
+                code,
+                reason: close_reason(&self.read_buf).into(),
+            },
+            Head::Error(err) => Event::Error(err),
+        }
+    }
+
+    /// Waits at most `dur` for the first byte of the next frame, then reads the rest of the frame.
+    ///
+    /// Returns `Ok(None)` if no frame has started within `dur`. Nothing is consumed from the stream in that case,
+    /// because a single read either completes or reads nothing.
+    async fn recv_head_within(&mut self, dur: Duration) -> Result<Option<Head>> {
+        let mut head = [0; 2];
+        let Ok(amt) = tokio::time::timeout(dur, self.stream.read(&mut head)).await else {
+            return Ok(None);
+        };
+        self.recv_frame(head, amt?).await.map(Some)
+    }
 
+    async fn read_frame(&mut self, [b1, b2]: [u8; 2]) -> Result<Head> {
         let fin = b1 & 0b_1000_0000 != 0;
         let rsv = b1 & 0b_111_0000;
         let opcode = b1 & 0b_1111;
@@ -198,12 +769,12 @@ where
         // client to server have this bit set to 1.
         let is_masked = b2 & 0b_1000_0000 != 0;
 
-        if rsv != 0 {
+        if rsv & !self.extension_rsv != 0 {
             // MUST be `0` unless an extension is negotiated that defines meanings
             // for non-zero values.  If a nonzero value is received and none of
             // the negotiated extensions defines the meaning of such a nonzero
             // value, the receiving endpoint MUST _Fail the WebSocket Connection_.
-            err!("reserve bit must be `0`");
+            err!(ProtocolError::ReservedBits);
         }
 
         // A client MUST mask all frames that it sends to the server. (Note
@@ -214,76 +785,361 @@ where
         // A server MUST NOT mask any frames that it sends to the client.
         if let Role::Server = self.role {
             if !is_masked {
-                err!("expected masked frame");
+                err!(ProtocolError::ExpectedMaskedFrame);
             }
         } else if is_masked {
-            err!("expected unmasked frame");
+            err!(ProtocolError::ExpectedUnmaskedFrame);
         }
 
-        // 3-7 are reserved for further non-control frames.
-        if opcode >= 8 {
+        // 3-7 are reserved for further non-control frames,
+        // 11-15 are reserved for further control frames.
+        let Some(opcode) = OpCode::from_u8(opcode) else {
+            err!(ProtocolError::UnknownOpCode);
+        };
+
+        if opcode.is_control() {
+            // Extensions don't define reserved bits for control frames.
+            if rsv != 0 {
+                err!(ProtocolError::ReservedBits);
+            }
             if !fin {
-                err!("control frame must not be fragmented");
+                err!(ProtocolError::FragmentedControlFrame);
             }
             if len > 125 {
-                err!("control frame must have a payload length of 125 bytes or less");
+                err!(ProtocolError::ControlFrameTooLarge);
             }
-            let msg = self.read_payload(len).await?;
+            self.read_payload(len).await?;
             match opcode {
-                8 => Ok(on_close(&msg)),
-                9 => Ok(Event::Ping(msg)),
-                10 => Ok(Event::Pong(msg)),
-                // 11-15 are reserved for further control frames
-                _ => err!("unknown opcode"),
+                OpCode::Close => {
+                    let head = on_close(&self.read_buf);
+                    if let Head::Close(..) = head {
+                        self.close.set_received();
+                    }
+                    Ok(head)
+                }
+                OpCode::Ping => Ok(Head::Ping),
+                _ => Ok(Head::Pong),
             }
         } else {
             let ty = match (opcode, fin, self.fragment) {
-                (2, true, None) => DataType::Complete(MessageType::Binary),
-                (1, true, None) => DataType::Complete(MessageType::Text),
-                (2, false, None) => {
+                (OpCode::Binary, true, None) => DataType::Complete(MessageType::Binary),
+                (OpCode::Text, true, None) => DataType::Complete(MessageType::Text),
+                (OpCode::Binary, false, None) => {
                     self.fragment = Some(MessageType::Binary);
                     DataType::Stream(Stream::Start(MessageType::Binary))
                 }
-                (1, false, None) => {
+                (OpCode::Text, false, None) => {
                     self.fragment = Some(MessageType::Text);
                     DataType::Stream(Stream::Start(MessageType::Text))
                 }
-                (0, false, Some(ty)) => DataType::Stream(Stream::Next(ty)),
-                (0, true, Some(ty)) => {
+                (OpCode::Continuation, false, Some(ty)) => DataType::Stream(Stream::Next(ty)),
+                (OpCode::Continuation, true, Some(ty)) => {
                     self.fragment = None;
                     DataType::Stream(Stream::End(ty))
                 }
-                _ => err!("invalid data frame"),
+                _ => err!(ProtocolError::InvalidDataFrame),
+            };
+            // Extensions only set the reserved bits on the first frame of a message.
+            let rsv = match ty {
+                DataType::Complete(_) | DataType::Stream(Stream::Start(_)) => {
+                    self.message_rsv = rsv;
+                    rsv
+                }
+                _ if rsv != 0 => err!(ProtocolError::ReservedBits),
+                _ => self.message_rsv,
             };
             let len = match len {
                 126 => u16::from_be_bytes(read_buf(&mut self.stream).await?) as usize,
-                127 => u64::from_be_bytes(read_buf(&mut self.stream).await?) as usize,
+                127 => {
+                    let len = u64::from_be_bytes(read_buf(&mut self.stream).await?);
+                    // the most significant bit MUST be 0
+                    if len >> 63 != 0 {
+                        err!(ProtocolError::InvalidPayloadLen);
+                    }
+                    // Doesn't always fit in `usize` on 32-bit targets, it is rejected by `max_payload_len` below.
+                    usize::try_from(len).unwrap_or(usize::MAX)
+                }
                 len => len,
             };
             if len > self.max_payload_len {
-                err!("payload too large");
+                err!(ProtocolError::PayloadTooLarge);
+            }
+            let message_len = match ty {
+                DataType::Stream(Stream::Next(_) | Stream::End(_)) => self.message_len,
+                _ => 0,
+            };
+            if message_len.saturating_add(len) > self.max_message_len {
+                err!(ProtocolError::MessageTooLarge);
+            }
+            self.read_payload(len).await?;
+            let fin = matches!(ty, DataType::Complete(_) | DataType::Stream(Stream::End(_)));
+            match self
+                .incoming
+                .transform(&self.read_buf, rsv, fin, self.max_payload_len)
+            {
+                Ok(Some(data)) => self.read_buf = data,
+                Ok(None) => {}
+                Err(msg) => err!(msg),
             }
-            let data = self.read_payload(len).await?;
-            Ok(Event::Data { ty, data })
+
+            let data = &self.read_buf;
+            // The transformed (e.g. inflated) payload may exceed the limit.
+            self.message_len = message_len.saturating_add(data.len());
+            if self.message_len > self.max_message_len {
+                err!(ProtocolError::MessageTooLarge);
+            }
+
+            match ty {
+                DataType::Complete(MessageType::Text) if std::str::from_utf8(data).is_err() => {
+                    err!(ProtocolError::InvalidUtf8)
+                }
+                DataType::Stream(ref stream) if stream.ty().is_text() => {
+                    if let Stream::Start(_) = stream {
+                        self.utf8.reset();
+                    }
+                    match (self.utf8.feed(data), stream) {
+                        (Utf8Status::Invalid, _) | (Utf8Status::Incomplete, Stream::End(_)) => {
+                            err!(ProtocolError::InvalidUtf8)
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+            Ok(Head::Data(ty))
         }
     }
 
-    async fn read_payload(&mut self, len: usize) -> Result<Box<[u8]>> {
-        let mut data = vec![0; len].into_boxed_slice();
-        match self.role {
-            Role::Server => {
-                let mask: [u8; 4] = read_buf(&mut self.stream).await?;
-                self.stream.read_exact(&mut data).await?;
-                // TODO: Use SIMD wherever possible for best performance
-                for i in 0..data.len() {
-                    data[i] ^= mask[i & 3];
+    /// Reads the payload into `read_buf`.
+    async fn read_payload(&mut self, len: usize) -> Result<()> {
+        let mask = match self.role {
+            Role::Server => Some(read_buf::<4, _>(&mut self.stream).await?),
+            Role::Client => None,
+        };
+        self.read_buf.clear();
+        self.read_buf.resize(len, 0);
+        self.stream.read_exact(&mut self.read_buf).await?;
+        if let Some(mask) = mask {
+            unmask(&mut self.read_buf, mask, 0);
+        }
+        #[cfg(feature = "metrics")]
+        self.metrics.received(len);
+        Ok(())
+    }
+}
+
+impl<IO, M> WebSocket<IO, M>
+where
+    IO: Unpin + AsyncRead + AsyncWrite,
+    M: RandKey,
+{
+    /// reads [Event] from websocket stream.
+    ///
+    /// If [WebSocket::auto_pong] is enabled, a Pong frame is sent in response to every Ping frame.
+    /// The [Event::Ping] is still returned, so that it can be observed.
+    ///
+    /// On an [Event::Error], a Close frame with the status code of [ProtocolError::close_code] is sent.
+    ///
+    /// After an [Event::Close] (or an error) is returned, subsequent calls return [Event::Closed],
+    /// so the end of the connection can be told apart from a failure.
+    ///
+    /// If the connection ends without a Close frame, it's an `UnexpectedEof` error between messages,
+    /// and a `ConnectionAborted` error in the middle of a frame or a fragmented message.
+    pub async fn recv(&mut self) -> Result<Event> {
+        if self.close.received() {
+            return Ok(Event::Closed);
+        }
+        let head = self.recv_managed().await?;
+        Ok(self.take_event(head))
+    }
+
+    /// Same as [WebSocket::recv], but the payload borrows the receive buffer of the websocket, see [WebSocket::recv_event_ref].
+    ///
+    /// Small unfragmented messages are received without any allocation, once the buffer has grown to fit them.
+    /// The returned [EventRef] borrows the websocket, so it must be dropped before receiving or sending again.
+    pub async fn recv_ref(&mut self) -> Result<EventRef<'_>> {
+        if self.close.received() {
+            return Ok(EventRef::Closed);
+        }
+        let head = self.recv_managed().await?;
+        Ok(self.borrow_event(head))
+    }
+
+    async fn recv_managed(&mut self) -> Result<Head> {
+        let head = match self.keepalive {
+            Some(interval) => self.recv_keepalive(interval).await,
+            None => self.recv_head().await,
+        };
+        self.on_head(head).await
+    }
+
+    /// Same as [WebSocket::recv], but returns a `TimedOut` error if no frame starts within `dur`.
+    ///
+    /// The connection stays usable after a timeout, no partially received frame is lost.
+    /// [WebSocket::keepalive] pings are not sent while waiting.
+    pub async fn recv_timeout(&mut self, dur: Duration) -> Result<Event> {
+        if self.close.received() {
+            return Ok(Event::Closed);
+        }
+        let head = match self.recv_head_within(dur).await {
+            Ok(None) => io_err!(TimedOut, "no frame received within timeout"),
+            Ok(Some(head)) => Ok(head),
+            Err(err) => Err(err),
+        };
+        let head = self.on_head(head).await?;
+        Ok(self.take_event(head))
+    }
+
+    async fn on_head(&mut self, head: Result<Head>) -> Result<Head> {
+        #[cfg(feature = "tracing")]
+        if let Err(err) = &head {
+            tracing::debug!(%err, "connection failed");
+        }
+        match head {
+            Ok(Head::Ping) if self.auto_pong && !self.close.sent() => {
+                let data = std::mem::take(&mut self.read_buf);
+                let res = self.send_pong(&data).await;
+                self.read_buf = data;
+                res?;
+                self.stream.flush().await?;
+            }
+            // Echo the status code, as a response to the Close frame of the endpoint.
+            Ok(Head::Close(code)) if !self.close.sent() => match code {
+                Some(code) => self.send_close(code).await?,
+                None => self.send_close(()).await?,
+            },
+            // The connection has failed, nothing more can be received.
+            Ok(Head::Error(err)) => {
+                self.close.set_received();
+                if !self.close.sent() {
+                    self.send_close(err.close_code()).await?;
                 }
             }
-            Role::Client => {
-                self.stream.read_exact(&mut data).await?;
+            Err(..) => self.close.set_received(),
+            _ => {}
+        }
+        head
+    }
+
+    /// Perform the closing handshake.
+    ///
+    /// Sends a Close frame unless one was already sent, then discards incoming frames until the Close frame
+    /// of the endpoint arrives (or the connection ends). Finally, the write half of the stream is shut down.
+    ///
+    /// It waits for the endpoint indefinitely, wrap it with `tokio::time::timeout` to bound it.
+    ///
+    /// Returns an `InvalidInput` error if the status code must not be sent by an endpoint (e.g. `1005`, `1006`, `1015`),
+    /// or the reason is longer than 123 bytes.
+    pub async fn close<T>(&mut self, reason: T) -> Result<()>
+    where
+        T: CloseReason,
+        T::Bytes: AsRef<[u8]>,
+    {
+        if !self.close.sent() {
+            self.send_close(reason).await?;
+        }
+        while !self.close.received() {
+            match self.recv_event().await {
+                Ok(Event::Error(..)) => self.close.set_received(),
+                Ok(_) => {}
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        std::io::ErrorKind::UnexpectedEof | std::io::ErrorKind::ConnectionAborted
+                    ) =>
+                {
+                    self.close.set_received()
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!("closing handshake completed");
+        self.stream.shutdown().await
+    }
+
+    async fn recv_keepalive(&mut self, interval: Duration) -> Result<Head> {
+        let mut ping_sent = false;
+        loop {
+            if let Some(head) = self.recv_head_within(interval).await? {
+                return Ok(head);
+            }
+            if ping_sent {
+                io_err!(TimedOut, "keepalive timed out");
+            }
+            self.send_ping([]).await?;
+            self.stream.flush().await?;
+            ping_sent = true;
+        }
+    }
+
+    /// reads a complete [Message] from websocket stream, reassembling fragmented messages.
+    ///
+    /// Control frames may arrive between the fragments of a message. They are returned as soon as they are received,
+    /// and the partially received message is kept until its final fragment arrives.
+    ///
+    /// Protocol errors are returned as an `InvalidData` error.
+    /// After a [Message::Close] (or an error) is returned, subsequent calls return [Message::Closed].
+    pub async fn recv_message(&mut self) -> Result<Message> {
+        loop {
+            let (ty, data) = match self.recv().await? {
+                Event::Data {
+                    ty: DataType::Complete(ty),
+                    data,
+                } => (ty, data.into_vec()),
+                Event::Data {
+                    ty: DataType::Stream(stream),
+                    data,
+                } => {
+                    self.message.extend_from_slice(&data);
+                    match stream {
+                        Stream::End(ty) => (ty, std::mem::take(&mut self.message)),
+                        _ => continue,
+                    }
+                }
+                Event::Ping(data) => return Ok(Message::Ping(data.into_vec())),
+                Event::Pong(data) => return Ok(Message::Pong(data.into_vec())),
+                Event::Close { code, reason } => {
+                    return Ok(Message::Close {
+                        code,
+                        reason: reason.into_string(),
+                    })
+                }
+                Event::Error(err) => io_err!(InvalidData, err),
+                Event::Closed => return Ok(Message::Closed),
+            };
+            return match ty {
+                MessageType::Binary => Ok(Message::Binary(data)),
+                MessageType::Text => match String::from_utf8(data) {
+                    Ok(text) => Ok(Message::Text(text)),
+                    Err(_) => io_err!(InvalidData, ProtocolError::InvalidUtf8),
+                },
+            };
+        }
+    }
+
+    /// Appends the payload of the next data message to `buf` and returns its type, `None` once a Close frame is received
+    /// or the connection has ended.
+    ///
+    /// Fragments are reassembled directly into `buf`, so it can be reused across messages.
+    /// Ping and Pong frames are skipped, after being handled as in [WebSocket::recv].
+    ///
+    /// The payload of a text message is valid UTF-8, e.g. `String::from_utf8(buf)` doesn't fail.
+    /// The message is limited by [WebSocket::max_message_len], on error `buf` may contain part of it.
+    pub async fn recv_message_into(&mut self, buf: &mut Vec<u8>) -> Result<Option<MessageType>> {
+        loop {
+            match self.recv().await? {
+                Event::Data { ty, data } => {
+                    buf.extend_from_slice(&data);
+                    if let DataType::Complete(ty) | DataType::Stream(Stream::End(ty)) = ty {
+                        return Ok(Some(ty));
+                    }
+                }
+                Event::Ping(_) | Event::Pong(_) => {}
+                Event::Close { .. } | Event::Closed => return Ok(None),
+                Event::Error(err) => io_err!(InvalidData, err),
             }
         }
-        Ok(data)
     }
 }
 
@@ -303,39 +1159,64 @@ where
 /// - After both sending and receiving a Close message, an endpoint
 ///   considers the WebSocket connection closed and MUST close the
 ///   underlying TCP connection.
-fn on_close(msg: &[u8]) -> Event {
-    let code = msg
-        .get(..2)
-        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
-        .unwrap_or(1000);
-
-    match code {
-        1000..=1003 | 1007..=1011 | 1015 | 3000..=3999 | 4000..=4999 => {
-            match msg.get(2..).map(|data| String::from_utf8(data.to_vec())) {
-                Some(Ok(msg)) => Event::Close {
-                    code,
-                    reason: msg.into_boxed_str(),
-                },
-                None => Event::Close {
-                    code,
-                    reason: "".into(),
-                },
-                Some(Err(_)) => Event::Error("invalid utf-8 payload"),
-            }
-        }
-        _ => Event::Error("invalid close code"),
+fn on_close(msg: &[u8]) -> Head {
+    if msg.is_empty() {
+        return Head::Close(None);
+    }
+    let Some((code, reason)) = msg.split_first_chunk() else {
+        // a single byte can't be a status code.
+        return Head::Error(ProtocolError::InvalidCloseCode);
+    };
+    let code = u16::from_be_bytes(*code);
+    if CloseCode::from_u16(code).is_none() {
+        return Head::Error(ProtocolError::InvalidCloseCode);
+    }
+    match std::str::from_utf8(reason) {
+        Ok(_) => Head::Close(Some(code)),
+        Err(_) => Head::Error(ProtocolError::InvalidUtf8),
     }
 }
 
+#[cfg(feature = "tracing")]
+fn trace_sent(frame: &Frame, rsv: u8) {
+    tracing::trace!(opcode = ?frame.opcode, fin = frame.fin, rsv, len = frame.data.len(), "sending frame");
+}
+
+/// Reason of a Close frame body, validated by [on_close].
+fn close_reason(msg: &[u8]) -> &str {
+    msg.get(2..)
+        .and_then(|reason| std::str::from_utf8(reason).ok())
+        .unwrap_or_default()
+}
+
 impl<IO> From<(IO, Role)> for WebSocket<IO> {
     #[inline]
     fn from((stream, role): (IO, Role)) -> Self {
         Self {
             stream,
             max_payload_len: 16 * 1024 * 1024,
+            max_message_len: 64 * 1024 * 1024,
+            auto_pong: true,
+            keepalive: None,
+            #[cfg(feature = "deflate")]
+            compress_min_size: 0,
             role,
-            is_closed: false,
+            close: Default::default(),
             fragment: None,
+            message_rsv: 0,
+            message_len: 0,
+            utf8: Utf8Validator::default(),
+            message: Vec::new(),
+            read_buf: Vec::new(),
+            write_buf: Vec::new(),
+            mask: RandMask,
+            #[cfg(feature = "metrics")]
+            metrics: Default::default(),
+            #[cfg(feature = "metrics")]
+            buffered: (0, 0),
+            outgoing: OutgoingExtensions::default(),
+            incoming: IncomingExtensions::default(),
+            extension_rsv: 0,
         }
     }
 }
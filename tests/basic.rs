@@ -1,4 +1,12 @@
-use std::io;
+#![cfg(feature = "std")]
+
+use std::{
+    borrow::Cow,
+    io::{self, IoSlice},
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::io::AsyncWrite;
 use web_socket::*;
 
 const DATA: &str = "Hello";
@@ -20,20 +28,37 @@ async fn unmasked() -> io::Result<()> {
     ws.send_ping(DATA).await?;
     assert_eq!(writer, [0x89, 5, b'H', b'e', b'l', b'l', b'o']);
 
+    // ----------------------- owned messages -------------------------
+    writer.clear();
+
+    let mut ws = WebSocket::server(&mut writer);
+    ws.send(&DATA.to_string()).await?;
+    ws.send(&DATA.as_bytes().to_vec()).await?;
+    assert_eq!(writer[..2], [0x81, 5]);
+    assert_eq!(writer[7..9], [0x82, 5]);
+
+    writer.clear();
+
+    let mut ws = WebSocket::server(&mut writer);
+    ws.send(&Cow::Borrowed(DATA)).await?;
+    ws.send(&Cow::<[u8]>::Owned(DATA.into())).await?;
+    assert_eq!(writer[..2], [0x81, 5]);
+    assert_eq!(writer[7..9], [0x82, 5]);
+
     // ----------------------- streaming text -------------------------
     writer.clear();
 
     let mut ws = WebSocket::server(&mut writer);
     ws.send(Frame {
         fin: false,
-        opcode: MessageType::Text as u8,
+        opcode: OpCode::Text,
         data: b"Hel",
     })
     .await?;
 
     ws.send(Frame {
         fin: true,
-        opcode: 0,
+        opcode: OpCode::Continuation,
         data: b"lo",
     })
     .await?;
@@ -46,3 +71,240 @@ async fn unmasked() -> io::Result<()> {
     );
     Ok(())
 }
+
+#[tokio::test]
+async fn fragmented() -> io::Result<()> {
+    let mut writer = vec![];
+    let mut ws = WebSocket::server(&mut writer);
+    ws.send_fragmented(DATA, 2).await?;
+    assert_eq!(
+        writer,
+        [
+            0x01, 2, b'H', b'e', // first frame
+            0x00, 2, b'l', b'l', // continuation frame
+            0x80, 1, b'o', // final frame
+        ]
+    );
+
+    writer.clear();
+    let mut ws = WebSocket::server(&mut writer);
+    ws.send_fragmented(&b""[..], 2).await?;
+    ws.send_fragmented(DATA, 5).await?;
+
+    let err = ws.send_fragmented(DATA, 0).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    let ping = Frame {
+        fin: true,
+        opcode: OpCode::Ping,
+        data: b"",
+    };
+    let err = ws.send_fragmented(ping, 2).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(writer, [0x82, 0, 0x81, 5, b'H', b'e', b'l', b'l', b'o']);
+    Ok(())
+}
+
+#[tokio::test]
+async fn message_writer() -> io::Result<()> {
+    let mut writer = vec![];
+    let mut ws = WebSocket::server(&mut writer);
+    let mut message = ws.start_message(MessageType::Text, 2);
+    message.write(b"H").await?;
+    message.write(b"ell").await?;
+    message.write(b"").await?;
+    message.write(b"o").await?;
+    message.finish().await?;
+    assert_eq!(
+        writer,
+        [
+            0x01, 2, b'H', b'e', // first frame
+            0x00, 2, b'l', b'l', // continuation frame
+            0x80, 1, b'o', // final frame
+        ]
+    );
+
+    // a full fragment is sent by `finish`.
+    writer.clear();
+    let mut ws = WebSocket::server(&mut writer);
+    let mut message = ws.start_message(MessageType::Binary, 2);
+    message.write(b"Hi").await?;
+    message.finish().await?;
+    ws.start_message(MessageType::Binary, 2).finish().await?;
+    assert_eq!(writer, [0x82, 2, b'H', b'i', 0x82, 0]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn send_all() -> io::Result<()> {
+    let mut writer = vec![];
+    let mut ws = WebSocket::server(&mut writer);
+    ws.send_all(["Hi", "Hello"]).await?;
+    ws.send_all([&b"!"[..]]).await?;
+    ws.send_all(Vec::<Frame>::new()).await?;
+    assert_eq!(
+        writer,
+        [0x81, 2, b'H', b'i', 0x81, 5, b'H', b'e', b'l', b'l', b'o', 0x82, 1, b'!']
+    );
+
+    // nothing is sent if a frame is invalid.
+    writer.clear();
+    let mut ws = WebSocket::server(&mut writer);
+    let close = |data| Frame {
+        fin: true,
+        opcode: OpCode::Close,
+        data,
+    };
+    let err = ws.send_all([Frame::from("Hi"), close(&[0x03])]).await;
+    assert_eq!(err.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    let err = ws.send_all([close(&[]), Frame::from("Hi")]).await;
+    assert_eq!(err.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(ws.state(), State::Open);
+
+    ws.send_all([Frame::from("Hi"), close(&[])]).await?;
+    assert_eq!(ws.state(), State::Closing);
+    assert_eq!(writer, [0x81, 2, b'H', b'i', 0x88, 0]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn masked() -> io::Result<()> {
+    let mut writer = vec![];
+
+    // https://datatracker.ietf.org/doc/html/rfc6455#section-5.7
+    let mut ws = WebSocket::client(&mut writer).with_mask(|| [0x37, 0xfa, 0x21, 0x3d]);
+    ws.send(DATA).await?;
+    assert_eq!(
+        writer,
+        [0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58]
+    );
+    Ok(())
+}
+
+#[cfg(feature = "secure-mask")]
+#[tokio::test]
+async fn secure_mask() -> io::Result<()> {
+    let mut writer = vec![];
+
+    let mut ws = WebSocket::client(&mut writer).with_mask(mask::SecureMask);
+    ws.send(DATA).await?;
+    let [0x81, 0x85, m0, m1, m2, m3, a, b, c, d, e] = writer[..] else {
+        panic!("unexpected frame: {writer:?}");
+    };
+    let mask = [m0, m1, m2, m3];
+    let payload = [a, b, c, d, e];
+    let payload: Vec<u8> = (0..5).map(|i| payload[i] ^ mask[i % 4]).collect();
+    assert_eq!(payload, DATA.as_bytes());
+    Ok(())
+}
+
+#[tokio::test]
+async fn control_frame_limits() {
+    let mut writer = vec![];
+    let mut ws = WebSocket::server(&mut writer);
+
+    let err = ws.send_ping([0; 126]).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let err = ws
+        .send(Frame {
+            fin: false,
+            opcode: OpCode::Pong,
+            data: b"",
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let reason = "x".repeat(124);
+    let err = ws.send_close(reason.as_str()).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(writer.is_empty());
+}
+
+#[tokio::test]
+async fn close_code() -> io::Result<()> {
+    let mut writer = vec![];
+
+    for code in [0, 999, 1004, 1005, 1006, 1015, 2999, 5000] {
+        let err = WebSocket::server(&mut writer)
+            .send_close(code)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+    assert!(writer.is_empty());
+
+    WebSocket::server(&mut writer)
+        .send_close((CloseCode::Away, "bye"))
+        .await?;
+    assert_eq!(writer, [0x88, 5, 0x03, 0xE9, b'b', b'y', b'e']);
+
+    writer.clear();
+    WebSocket::server(&mut writer).send_close(()).await?;
+    assert_eq!(writer, [0x88, 0]);
+
+    // the longest reason that fits in a control frame.
+    writer.clear();
+    let reason = "x".repeat(123);
+    WebSocket::server(&mut writer)
+        .send_close(reason.as_str())
+        .await?;
+    assert_eq!(writer[..4], [0x88, 125, 0x03, 0xE8]);
+    assert_eq!(writer[4..], *reason.as_bytes());
+
+    // close frames sent with `send` are validated the same way.
+    writer.clear();
+    let mut ws = WebSocket::server(&mut writer);
+    for data in [&[0x03][..], &[0x03, 0xED], &[0x03, 0xE8, 0xff]] {
+        let frame = Frame {
+            fin: true,
+            opcode: OpCode::Close,
+            data,
+        };
+        let err = ws.send(frame).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+    assert!(writer.is_empty());
+    Ok(())
+}
+
+/// Accepts nothing, e.g. a closed pipe that reports it with `Ok(0)`.
+struct WriteZero;
+
+impl AsyncWrite for WriteZero {
+    fn poll_write(self: Pin<&mut Self>, _: &mut Context<'_>, _: &[u8]) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(0))
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        _: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(0))
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn write_zero() {
+    // the server writes the header and the payload with `write_vectored`.
+    let mut ws = WebSocket::server(WriteZero);
+    let err = ws.send(DATA).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+
+    let mut ws = WebSocket::client(WriteZero);
+    let err = ws.send(DATA).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+}
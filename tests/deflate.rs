@@ -0,0 +1,191 @@
+#![cfg(feature = "deflate")]
+
+use std::io;
+use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+use web_socket::{deflate::DeflateConfig, *};
+
+#[test]
+fn negotiate() {
+    let config = DeflateConfig::negotiate("permessage-deflate", Role::Server);
+    assert_eq!(config, Some(DeflateConfig::default()));
+
+    let offers = "x-webkit-deflate-frame, permessage-deflate; server_max_window_bits=10, permessage-deflate; client_max_window_bits; server_no_context_takeover";
+    let config = DeflateConfig::negotiate(offers, Role::Server).unwrap();
+    assert!(config.server_no_context_takeover && !config.client_no_context_takeover);
+    assert_eq!(
+        config.to_string(),
+        "permessage-deflate; server_no_context_takeover"
+    );
+
+    // we can't compress with a smaller window.
+    let res = "permessage-deflate; client_max_window_bits=9";
+    assert_eq!(DeflateConfig::negotiate(res, Role::Client), None);
+    assert!(DeflateConfig::negotiate(res, Role::Server).is_some());
+
+    for invalid in [
+        "permessage-deflate; unknown",
+        "permessage-deflate; server_max_window_bits",
+        "permessage-deflate; client_max_window_bits=16",
+        "permessage-deflate; server_no_context_takeover; server_no_context_takeover",
+    ] {
+        assert_eq!(DeflateConfig::negotiate(invalid, Role::Server), None);
+    }
+}
+
+// https://datatracker.ietf.org/doc/html/rfc7692#section-7.2.3.1
+#[tokio::test]
+async fn rfc_example() -> io::Result<()> {
+    let (stream, mut peer) = duplex(64);
+    let mut ws = WebSocket::client(stream);
+    ws.enable_deflate(DeflateConfig::default());
+
+    // compressed "Hello", in a single frame, and fragmented.
+    peer.write_all(&[0xc1, 0x07, 0xf2, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00])
+        .await?;
+    peer.write_all(&[0x41, 0x03, 0xf2, 0x48, 0xcd]).await?;
+    peer.write_all(&[0x80, 0x04, 0xc9, 0xc9, 0x07, 0x00])
+        .await?;
+    assert_eq!(ws.recv_message().await?, Message::Text("Hello".into()));
+    assert_eq!(ws.recv_message().await?, Message::Text("Hello".into()));
+
+    // `RSV1` must only be set on the first frame.
+    peer.write_all(&[0x41, 0x03, 0xf2, 0x48, 0xcd]).await?;
+    peer.write_all(&[0xc0, 0x04, 0xc9, 0xc9, 0x07, 0x00])
+        .await?;
+    assert!(ws.recv_message().await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn compressed_control_frame() -> io::Result<()> {
+    let (stream, mut peer) = duplex(64);
+    let mut ws = WebSocket::client(stream);
+    ws.enable_deflate(DeflateConfig::default());
+    peer.write_all(&[0x89 | RSV1, 0]).await?;
+    assert!(matches!(
+        ws.recv_event().await?,
+        Event::Error(ProtocolError::ReservedBits)
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn round_trip() -> io::Result<()> {
+    let (client, server) = duplex(1024);
+    let mut client = WebSocket::client(client);
+    let mut server = WebSocket::server(server);
+    let config = DeflateConfig {
+        server_no_context_takeover: false,
+        client_no_context_takeover: true,
+    };
+    client.enable_deflate(config);
+    server.enable_deflate(config);
+
+    let text = "Hello, World! ".repeat(100);
+    for _ in 0..3 {
+        client.send(text.as_str()).await?;
+        assert_eq!(server.recv_message().await?, Message::Text(text.clone()));
+
+        server.send(text.as_bytes()).await?;
+        assert_eq!(
+            client.recv_message().await?,
+            Message::Binary(text.clone().into_bytes())
+        );
+    }
+    server.send_all([text.as_str(), "Hi"]).await?;
+    assert_eq!(client.recv_message().await?, Message::Text(text.clone()));
+    assert_eq!(client.recv_message().await?, Message::Text("Hi".into()));
+
+    // an empty message is compressed to a single `0x00` byte.
+    server.send("").await?;
+    let mut buf = [0; 3];
+    client.stream.read_exact(&mut buf).await?;
+    assert_eq!(buf, [0xc1, 1, 0]);
+
+    // compressed as a whole, `RSV1` is set on the first frame only.
+    server.send_fragmented(text.as_str(), 8).await?;
+    assert_eq!(client.recv_message().await?, Message::Text(text.clone()));
+    server.send_fragmented(text.as_str(), 8).await?;
+    let mut head = [0; 2];
+    client.stream.read_exact(&mut head).await?;
+    assert_eq!(head, [0x41, 8]);
+    let mut rest = [0; 8];
+    client.stream.read_exact(&mut rest).await?;
+    let mut buf = rest.to_vec();
+    loop {
+        client.stream.read_exact(&mut head).await?;
+        assert_eq!(head[0] & 0x7F, 0, "continuation frame without `RSV1`");
+        let mut payload = vec![0; head[1] as usize];
+        client.stream.read_exact(&mut payload).await?;
+        buf.extend(payload);
+        if head[0] & 0x80 != 0 {
+            break;
+        }
+    }
+    assert!(buf.len() < text.len());
+    server.send_ping("uncompressed").await?;
+    assert_eq!(
+        client.recv_message().await?,
+        Message::Ping(b"uncompressed".to_vec())
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn uncompressed() -> io::Result<()> {
+    let (client, server) = duplex(1024);
+    let mut client = WebSocket::client(client);
+    let mut server = WebSocket::server(server);
+    client.enable_deflate(DeflateConfig::default());
+    server.enable_deflate(DeflateConfig::default());
+    server.compress_min_size = 4;
+
+    let mut buf = [0; 5];
+    server.send_uncompressed("Hello").await?;
+    client.stream.read_exact(&mut buf[..2]).await?;
+    assert_eq!(buf[..2], [0x81, 5]);
+    client.stream.read_exact(&mut buf).await?;
+
+    server.send("Hi").await?;
+    client.stream.read_exact(&mut buf[..4]).await?;
+    assert_eq!(buf[..4], [0x81, 2, b'H', b'i']);
+
+    // still compressed above the threshold, and both kinds are received.
+    server.send("Hello").await?;
+    server.send_uncompressed("Hello").await?;
+    assert_eq!(client.recv_message().await?, Message::Text("Hello".into()));
+    assert_eq!(client.recv_message().await?, Message::Text("Hello".into()));
+    Ok(())
+}
+
+#[tokio::test]
+async fn decompressed_payload_limit() -> io::Result<()> {
+    let (client, server) = duplex(1024 * 1024);
+    let mut client = WebSocket::client(client);
+    let mut server = WebSocket::server(server);
+    client.enable_deflate(DeflateConfig::default());
+    server.enable_deflate(DeflateConfig::default());
+    client.max_payload_len = 1000;
+
+    server.send(&[0; 1001][..]).await?;
+    let event = client.recv_event().await?;
+    assert!(matches!(
+        event,
+        Event::Error(ProtocolError::PayloadTooLarge)
+    ));
+
+    // the compressed payload is within the limit, the message isn't.
+    let (client, server) = duplex(1024 * 1024);
+    let mut client = WebSocket::client(client);
+    let mut server = WebSocket::server(server);
+    client.enable_deflate(DeflateConfig::default());
+    server.enable_deflate(DeflateConfig::default());
+    client.max_message_len = 1000;
+    server.send(&[0; 1001][..]).await?;
+    let event = client.recv_event().await?;
+    assert!(matches!(
+        event,
+        Event::Error(ProtocolError::MessageTooLarge)
+    ));
+    Ok(())
+}
@@ -0,0 +1,162 @@
+#![cfg(feature = "std")]
+
+use std::io;
+use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+use web_socket::{extension::*, *};
+
+/// Reverses the payload of the messages, and counts the frames it transformed.
+#[derive(Default, Clone)]
+struct Reverse {
+    frames: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+}
+
+impl Extension for Reverse {
+    type Outgoing = Self;
+    type Incoming = Self;
+
+    fn rsv(&self) -> u8 {
+        RSV2
+    }
+
+    fn split(self) -> (Self, Self) {
+        (self.clone(), self)
+    }
+}
+
+impl Outgoing for Reverse {
+    fn transform_outgoing(&mut self, data: &[u8], rsv: &mut u8) -> io::Result<Option<Vec<u8>>> {
+        *rsv |= RSV2;
+        Ok(Some(data.iter().rev().copied().collect()))
+    }
+}
+
+impl Incoming for Reverse {
+    fn transform_incoming(
+        &mut self,
+        data: &[u8],
+        rsv: u8,
+        _fin: bool,
+        limit: usize,
+    ) -> Result<Option<Vec<u8>>, ProtocolError> {
+        if rsv & RSV2 == 0 {
+            return Ok(None);
+        }
+        if data.len() > limit {
+            return Err(ProtocolError::PayloadTooLarge);
+        }
+        self.frames
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Ok(Some(data.iter().rev().copied().collect()))
+    }
+}
+
+#[tokio::test]
+async fn round_trip() -> io::Result<()> {
+    let (client, server) = duplex(4096);
+    let mut client = WebSocket::client(client);
+    let mut server = WebSocket::server(server);
+    client.add_extension(Reverse::default());
+    server.add_extension(Reverse::default());
+
+    client.send("Hello").await?;
+    client.send_fragmented("Hello, World", 5).await?;
+    client.send_all(["one", "two"]).await?;
+    client.send_ping("ping").await?;
+    assert_eq!(server.recv_message().await?, Message::Text("Hello".into()));
+    // each fragment is reversed separately by our extension.
+    assert_eq!(
+        server.recv_message().await?,
+        Message::Text("Worldllo, He".into())
+    );
+    assert_eq!(server.recv_message().await?, Message::Text("one".into()));
+    assert_eq!(server.recv_message().await?, Message::Text("two".into()));
+    assert_eq!(
+        server.recv_message().await?,
+        Message::Ping(b"ping".to_vec())
+    );
+
+    // messages sent without the extension are received as they are.
+    client.send_raw(Frame::from("Hello")).await?;
+    assert_eq!(server.recv_message().await?, Message::Text("Hello".into()));
+    Ok(())
+}
+
+#[tokio::test]
+async fn wire_format() -> io::Result<()> {
+    let (stream, mut peer) = duplex(64);
+    let mut ws = WebSocket::server(stream);
+    ws.add_extension(Reverse::default());
+    ws.send("abc").await?;
+
+    let mut buf = [0; 5];
+    peer.read_exact(&mut buf).await?;
+    assert_eq!(buf, [0x80 | RSV2 | 0x1, 3, b'c', b'b', b'a']);
+    Ok(())
+}
+
+#[tokio::test]
+async fn reserved_bits() -> io::Result<()> {
+    let (stream, mut peer) = duplex(64);
+    let mut ws = WebSocket::client(stream);
+    ws.add_extension(Reverse::default());
+
+    // the first frame of a message sets the bits of the whole message.
+    peer.write_all(&[0x01 | RSV2, 2, b'b', b'a']).await?;
+    peer.write_all(&[0x80, 1, b'c']).await?;
+    assert_eq!(ws.recv_message().await?, Message::Text("abc".into()));
+
+    for frames in [
+        // continuation frame with a reserved bit
+        &[0x01 | RSV2, 1, b'a', 0x80 | RSV2, 1, b'b'][..],
+        // bit that isn't defined by an extension
+        &[0x81 | 0x10, 1, b'a'],
+        // control frame with a reserved bit
+        &[0x89 | RSV2, 0],
+    ] {
+        let (stream, mut peer) = duplex(64);
+        let mut ws = WebSocket::client(stream);
+        ws.add_extension(Reverse::default());
+        peer.write_all(frames).await?;
+        let event = loop {
+            match ws.recv_event().await? {
+                Event::Data { .. } => {}
+                event => break event,
+            }
+        };
+        assert!(
+            matches!(event, Event::Error(ProtocolError::ReservedBits)),
+            "{frames:?}"
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn split() -> io::Result<()> {
+    let (client, server) = duplex(4096);
+    let mut client = WebSocket::client(client);
+    let mut server = WebSocket::server(server);
+    let reverse = Reverse::default();
+    let frames = reverse.frames.clone();
+    client.add_extension(Reverse::default());
+    server.add_extension(reverse);
+
+    let (mut reader, mut writer) = server.split();
+    client.send("Hello").await?;
+    let Event::Data { data, .. } = reader.recv_event().await? else {
+        panic!("expected data event")
+    };
+    assert_eq!(&*data, b"Hello");
+    writer.send("World").await?;
+    assert_eq!(client.recv_message().await?, Message::Text("World".into()));
+    assert_eq!(frames.load(std::sync::atomic::Ordering::Relaxed), 1);
+    Ok(())
+}
+
+#[test]
+#[should_panic = "reserved bits are used by another extension"]
+fn conflicting_rsv() {
+    let mut ws = WebSocket::client(Vec::<u8>::new());
+    ws.add_extension(Reverse::default());
+    ws.add_extension(Reverse::default());
+}
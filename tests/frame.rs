@@ -0,0 +1,186 @@
+use web_socket::*;
+
+#[test]
+fn decode_header() {
+    // ----------------------- 7-bit length ---------------------------
+    let buf = Frame::from("Hello").encode_without_mask();
+    let frame = decode(&buf).unwrap().unwrap();
+    assert_eq!(
+        frame,
+        DecodedFrame {
+            fin: true,
+            rsv: 0,
+            opcode: OpCode::Text,
+            mask: None,
+            offset: 2,
+            len: 5
+        }
+    );
+    assert_eq!(frame.payload(&buf), Some(&b"Hello"[..]));
+    assert_eq!(Frame::from("Hello").encoded_len(false), buf.len());
+
+    // ----------------------- 16-bit length --------------------------
+    let data = vec![0; 300];
+    let buf = Frame::from(&data[..]).encode_with([1, 2, 3, 4]);
+    let frame = decode(&buf).unwrap().unwrap();
+    assert_eq!(
+        (frame.opcode, frame.mask),
+        (OpCode::Binary, Some([1, 2, 3, 4]))
+    );
+    assert_eq!((frame.offset, frame.len), (8, 300));
+
+    let mut buf = vec![];
+    let written = Frame::from(&data[..]).encode_with_into([1, 2, 3, 4], &mut buf);
+    assert_eq!(written, 8 + 300);
+    assert_eq!(written, buf.len());
+
+    // ----------------------- 64-bit length --------------------------
+    let data = vec![0; 65536];
+    let buf = Frame::from(&data[..]).encode_without_mask();
+    let frame = decode(&buf).unwrap().unwrap();
+    assert_eq!((frame.offset, frame.len), (10, 65536));
+    assert_eq!(frame.payload(&buf).unwrap().len(), 65536);
+    assert_eq!(frame.payload(&buf[..100]), None);
+
+    let mut sink = std::collections::VecDeque::new();
+    let written = Frame::from(&data[..]).encode_to(None, &mut sink);
+    assert_eq!((written, sink.len()), (10 + 65536, buf.len()));
+
+    // ----------------------- incomplete header ----------------------
+    for len in 0..10 {
+        assert_eq!(decode(&buf[..len]), Ok(None));
+    }
+    let buf = Frame::from("Hello").encode_with([1, 2, 3, 4]);
+    assert_eq!(decode(&buf[..5]), Ok(None));
+}
+
+#[test]
+fn decode_reserved_opcode() {
+    for opcode in (3..8).chain(11..16) {
+        assert_eq!(
+            decode(&[0x80 | opcode, 0]),
+            Err(ProtocolError::UnknownOpCode)
+        );
+    }
+}
+
+#[test]
+fn decode_payload_len_msb() {
+    let mut buf = [0x82, 127, 0x80, 0, 0, 0, 0, 0, 0, 1];
+    assert_eq!(decode(&buf), Err(ProtocolError::InvalidPayloadLen));
+    buf[2] = 0;
+    assert_eq!(decode(&buf).unwrap().unwrap().len, 1);
+}
+
+#[test]
+fn decode_rsv() {
+    let frame = decode(&[0xC1, 0]).unwrap().unwrap();
+    assert_eq!((frame.rsv, frame.opcode), (0x40, OpCode::Text));
+
+    let frame = decode(&[0xB2, 0]).unwrap().unwrap();
+    assert_eq!((frame.rsv, frame.opcode), (0x30, OpCode::Binary));
+}
+
+#[test]
+fn encode_rsv() {
+    for (rsv, mask) in [(0, None), (0x40, None), (0x70, Some([1, 2, 3, 4]))] {
+        let mut buf = vec![];
+        let written = Frame::from("Hello").encode_with_rsv_into(rsv, mask, &mut buf);
+        assert_eq!(written, buf.len());
+        let frame = decode(&buf).unwrap().unwrap();
+        assert_eq!(
+            (frame.rsv, frame.opcode, frame.mask),
+            (rsv, OpCode::Text, mask)
+        );
+    }
+    let mut buf = vec![];
+    Frame::from("Hello").encode_with_rsv_into(0, None, &mut buf);
+    assert_eq!(buf, Frame::from("Hello").encode_without_mask());
+}
+
+#[test]
+#[should_panic = "invalid rsv bits"]
+fn encode_invalid_rsv() {
+    Frame::from("Hello").encode_with_rsv_into(0x80, None, &mut vec![]);
+}
+
+#[test]
+fn masked_payload() {
+    let mask = [0x37, 0xfa, 0x21, 0x3d];
+    for len in (0..=40).chain([125, 126, 1000, 70_000]) {
+        let data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        let buf = Frame::from(&data[..]).encode_with(mask);
+        let frame = decode(&buf).unwrap().unwrap();
+        let payload = frame.payload(&buf).unwrap();
+        assert_eq!(payload.len(), len);
+        for (i, byte) in payload.iter().enumerate() {
+            assert_eq!(byte ^ mask[i % 4], data[i], "len: {len}, index: {i}");
+        }
+    }
+}
+
+#[test]
+fn unmask_with_offset() {
+    // masked "Hello" from the RFC, see Section 5.7
+    let key = [0x37, 0xfa, 0x21, 0x3d];
+    let masked = [0x7f, 0x9f, 0x4d, 0x51, 0x58];
+    let mut data = masked;
+    mask::unmask(&mut data, key, 0);
+    assert_eq!(&data, b"Hello");
+
+    let payload: Vec<u8> = (0..100).map(|i| i as u8).collect();
+    let mut masked = payload.clone();
+    mask::unmask(&mut masked, key, 0);
+    for at in [0, 1, 2, 3, 5, 9, 17, 99] {
+        let mut data = masked.clone();
+        let (head, tail) = data.split_at_mut(at);
+        mask::unmask(head, key, 0);
+        mask::unmask(tail, key, at);
+        assert_eq!(data, payload, "split at {at}");
+    }
+}
+
+#[test]
+fn encode_into_non_empty_buffer() {
+    let data = vec![7; 70_000];
+    for mask in [None, Some([1, 2, 3, 4])] {
+        let mut buf = b"prefix".to_vec();
+        match mask {
+            Some(mask) => Frame::from(&data[..]).encode_with_into(mask, &mut buf),
+            None => Frame::from(&data[..]).encode_without_mask_into(&mut buf),
+        };
+        let (prefix, buf) = buf.split_at(6);
+        assert_eq!(prefix, b"prefix");
+
+        let frame = decode(buf).unwrap().unwrap();
+        assert_eq!((frame.mask, frame.len), (mask, data.len()));
+        assert_eq!(buf.len(), frame.offset + frame.len);
+        let payload = frame.payload(buf).unwrap();
+        let mask = mask.unwrap_or_default();
+        assert!(payload
+            .iter()
+            .enumerate()
+            .all(|(i, v)| v ^ mask[i % 4] == 7));
+    }
+}
+
+#[test]
+fn encode_to_sink() {
+    let data: Vec<u8> = (0..2000).map(|i| i as u8).collect();
+    for len in [0, 5, 300, 513, 2000] {
+        let frame = Frame::from(&data[..len]);
+        for mask in [None, Some([1, 2, 3, 4])] {
+            let mut ring = std::collections::VecDeque::from(vec![0; 3]);
+            ring.drain(..2);
+            frame.encode_to(mask, &mut ring);
+            ring.pop_front();
+
+            let mut buf = vec![];
+            match mask {
+                Some(mask) => frame.encode_with_into(mask, &mut buf),
+                None => frame.encode_without_mask_into(&mut buf),
+            };
+            assert_eq!(ring, buf);
+        }
+    }
+}
@@ -0,0 +1,681 @@
+#![cfg(feature = "std")]
+
+#[allow(dead_code)]
+#[path = "../examples/utils/mod.rs"]
+mod utils;
+
+use futures_util::StreamExt;
+use std::time::Duration;
+use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt, BufReader, DuplexStream};
+use utils::handshake;
+use web_socket::{CloseCode, Event, Frame, Message, OpCode};
+
+/// Spawns a server that answers the client's request key with `res`.
+fn respond<R: AsRef<[u8]> + Send>(res: impl FnOnce(&str) -> R + Send + 'static) -> DuplexStream {
+    respond_with(4096, res)
+}
+
+/// Same as [respond], with a transport that carries at most `max_buf_size` bytes per read.
+fn respond_with<R: AsRef<[u8]> + Send>(
+    max_buf_size: usize,
+    res: impl FnOnce(&str) -> R + Send + 'static,
+) -> DuplexStream {
+    let (client, server) = duplex(max_buf_size);
+    tokio::spawn(async move {
+        let mut server = BufReader::new(server);
+        let req = utils::Http::parse(&mut server).await.unwrap();
+        let res = res(&req["sec-websocket-key"]);
+        server.write_all(res.as_ref()).await.unwrap();
+    });
+    client
+}
+
+/// Sends a raw handshake `req` to [utils::accept], returns the server's response and error.
+async fn reject(req: &str) -> (String, std::io::Error) {
+    let (mut client, server) = duplex(4096);
+    let server = tokio::spawn(utils::accept(server));
+    client.write_all(req.as_bytes()).await.unwrap();
+    let err = match server.await.unwrap() {
+        Ok(_) => panic!("expected the handshake to fail"),
+        Err(err) => err,
+    };
+    let mut res = String::new();
+    client.read_to_string(&mut res).await.unwrap();
+    (res, err)
+}
+
+#[test]
+fn sec_ws_key() {
+    let (req, key) = handshake::request("example.com", "/", None::<(&str, &str)>);
+    assert!(req.contains(&format!("Sec-WebSocket-Key: {key}\r\n")));
+    assert_eq!(key.len(), 24);
+
+    let (_, other) = handshake::request("example.com", "/", None::<(&str, &str)>);
+    assert_ne!(key, other);
+}
+
+#[test]
+fn upgrade_headers() {
+    let (key, headers) = handshake::request_headers();
+    assert_eq!(headers[3], ("Sec-WebSocket-Key", key.clone()));
+
+    let [_, _, accept] = handshake::response_headers(&key);
+    assert_eq!(accept.0, "Sec-WebSocket-Accept");
+    assert!(handshake::verify_accept_key(&key, accept.1));
+
+    let res = handshake::response(
+        "dGhlIHNhbXBsZSBub25jZQ==",
+        [("Sec-WebSocket-Protocol", "chat")],
+    );
+    assert_eq!(res, "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\nSec-WebSocket-Protocol: chat\r\n\r\n");
+}
+
+#[test]
+fn validate_request() {
+    let key = "dGhlIHNhbXBsZSBub25jZQ==";
+    assert_eq!(
+        handshake::accept_key_from(key),
+        "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+    );
+
+    let (request, key) = handshake::request("example.com", "/chat", None::<(&str, &str)>);
+    assert_eq!(handshake::validate_request(&request), Some(key.as_str()));
+
+    let req = "GET / HTTP/1.1\r\nupgrade: WebSocket\r\nCONNECTION: keep-alive, upgrade\r\nsec-websocket-version: 13\r\nSec-Websocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n";
+    assert_eq!(
+        handshake::validate_request(req),
+        Some("dGhlIHNhbXBsZSBub25jZQ==")
+    );
+
+    for (from, to) in [
+        ("GET", "POST"),
+        ("websocket", "h2c"),
+        ("Connection: Upgrade", "Connection: keep-alive"),
+        ("Version: 13", "Version: 8"),
+        // missing padding
+        (key.as_str(), &key[..22]),
+        ("Host: example.com", "Host"),
+    ] {
+        let req = request.replace(from, to);
+        assert_eq!(handshake::validate_request(&req), None, "{from} -> {to}");
+    }
+}
+
+#[test]
+fn parse_url() {
+    use handshake::Url;
+
+    let url = Url::parse("ws://example.com").unwrap();
+    assert_eq!(
+        (url.secure, url.host, url.port, url.path),
+        (false, "example.com", 80, "/")
+    );
+
+    let url = Url::parse("WSS://example.com/chat").unwrap();
+    assert_eq!((url.secure, url.port, url.path), (true, 443, "/chat"));
+
+    let url = Url::parse("ws://127.0.0.1:9001?agent=web-socket").unwrap();
+    assert_eq!(
+        (url.host, url.port, url.path),
+        ("127.0.0.1", 9001, "?agent=web-socket")
+    );
+    assert_eq!(url.authority(), "127.0.0.1:9001");
+    assert_eq!(
+        Url::parse("ws://example.com:80").unwrap().authority(),
+        "example.com"
+    );
+    assert_eq!(
+        Url::parse("wss://example.com:80").unwrap().authority(),
+        "example.com:80"
+    );
+    assert_eq!(
+        Url::parse("wss://example.com:443").unwrap().authority(),
+        "example.com"
+    );
+
+    let url = Url::parse("wss://[::1]:8443/").unwrap();
+    assert_eq!((url.host, url.port), ("::1", 8443));
+    assert_eq!(url.authority(), "[::1]:8443");
+    assert_eq!(Url::parse("ws://[::1]/").unwrap().authority(), "[::1]");
+
+    for invalid in [
+        "http://example.com",
+        "example.com",
+        "ws://",
+        "ws://:80",
+        "ws://example.com:port",
+        "ws://example.com:70000",
+        "ws://user@example.com",
+        "ws://example.com/#fragment",
+        "ws://[::1/",
+    ] {
+        assert_eq!(Url::parse(invalid), None, "{invalid}");
+    }
+}
+
+#[test]
+fn request_headers() {
+    let headers = [("Origin", "https://example.com")];
+    let (req, _) = handshake::request("example.com:8080", "/chat", headers);
+    assert!(req.starts_with("GET /chat HTTP/1.1\r\nHost: example.com:8080\r\n"));
+    assert!(req.ends_with("Origin: https://example.com\r\n\r\n"));
+}
+
+#[tokio::test]
+async fn unsupported_version() {
+    let (req, _) = handshake::request("localhost", "/", [("Origin", "http://localhost")]);
+    assert!(req.contains("\r\nSec-WebSocket-Version: 13\r\n"));
+
+    let req = req.replace("Sec-WebSocket-Version: 13", "Sec-WebSocket-Version: 8");
+    let (res, err) = reject(&req).await;
+    assert_eq!(
+        res,
+        "HTTP/1.1 426 Upgrade Required\r\nSec-WebSocket-Version: 13\r\n\r\n"
+    );
+    assert_eq!(err.to_string(), "unsupported websocket version");
+}
+
+#[tokio::test]
+async fn invalid_upgrade_request() {
+    let (req, _) = handshake::request("localhost", "/", [("Origin", "http://localhost")]);
+    let key_line = req
+        .lines()
+        .find(|line| line.starts_with("Sec-WebSocket-Key"))
+        .unwrap();
+    let requests = [
+        req.replacen("GET", "POST", 1),
+        req.replace("Upgrade: websocket\r\n", ""),
+        req.replace("Upgrade: websocket", "Upgrade: h2c"),
+        req.replace("Connection: Upgrade", "Connection: keep-alive"),
+        req.replace(&format!("{key_line}\r\n"), ""),
+        req.replace(key_line, "Sec-WebSocket-Key: c2hvcnQ="),
+        req.replace("Sec-WebSocket-Version: 13\r\n", ""),
+    ];
+    for req in requests {
+        let (res, err) = reject(&req).await;
+        assert_eq!(res, "HTTP/1.1 400 Bad Request\r\n\r\n", "{req}");
+        assert_eq!(err.to_string(), "expected websocket upgrade request");
+    }
+
+    // Header values are case-insensitive tokens.
+    let req = req
+        .replace("Upgrade: websocket", "Upgrade: WebSocket")
+        .replace("Connection: Upgrade", "Connection: keep-alive, upgrade");
+    let (mut client, server) = duplex(4096);
+    let server = tokio::spawn(utils::accept(server));
+    client.write_all(req.as_bytes()).await.unwrap();
+    let res = utils::Http::parse(&mut BufReader::new(client))
+        .await
+        .unwrap();
+    assert_eq!(res.prefix(), "HTTP/1.1 101 Switching Protocols");
+    server.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn subprotocol() {
+    let (client, server) = duplex(4096);
+    let server = tokio::spawn(utils::accept_with(server, &[], |offers| {
+        assert_eq!(offers, ["chat", "graphql-ws"]);
+        offers.iter().find(|p| **p == "graphql-ws").copied()
+    }));
+    let (_, res) = utils::client(client, "localhost", "/", &[], &["chat", "graphql-ws"])
+        .await
+        .unwrap();
+    assert_eq!(res.prefix(), "HTTP/1.1 101 Switching Protocols");
+    assert_eq!(res["sec-websocket-protocol"], "graphql-ws");
+    server.await.unwrap().unwrap();
+
+    let (client, server) = duplex(4096);
+    let server = tokio::spawn(utils::accept_with(server, &[], |_| unreachable!()));
+    let (_, res) = utils::client(client, "localhost", "/", &[], &[])
+        .await
+        .unwrap();
+    assert_eq!(res.get("sec-websocket-protocol"), None);
+    server.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn unexpected_subprotocol() {
+    let client = respond(|key| handshake::response(key, [("Sec-WebSocket-Protocol", "mqtt")]));
+    let err = utils::client(client, "localhost", "/", &[], &["chat"])
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[tokio::test]
+async fn response_headers() {
+    let client = respond(|key| {
+        let headers = [
+            ("Set-Cookie", "session=1"),
+            ("X-Token", "abc"),
+            ("x-token", "def"),
+        ];
+        handshake::response(key, headers)
+    });
+    let (_, res) = utils::client(client, "localhost", "/", &[], &[])
+        .await
+        .unwrap();
+    assert_eq!(res["set-cookie"], "session=1");
+    assert_eq!(res["x-token"], "abc, def");
+}
+
+#[tokio::test]
+async fn server_response_headers() {
+    let (client, server) = duplex(4096);
+    let headers = [("Set-Cookie", "session=1"), ("X-Server", "test")];
+    let server = tokio::spawn(async move {
+        utils::accept_with(server, &headers, |offers| offers.first().copied()).await
+    });
+    let (_, res) = utils::client(client, "localhost", "/", &[], &["chat"])
+        .await
+        .unwrap();
+    assert_eq!(res["sec-websocket-protocol"], "chat");
+    assert_eq!(res["set-cookie"], "session=1");
+    assert_eq!(res["x-server"], "test");
+    server.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn check_origin() {
+    let server =
+        utils::ServerBuilder::new().check_origin(|origin| origin == Some("https://example.com"));
+    for (headers, allowed) in [
+        (&[("Origin", "https://example.com")][..], true),
+        (&[("Origin", "https://evil.com")], false),
+        (&[], false),
+    ] {
+        let (client, stream) = duplex(4096);
+        let server = server.clone();
+        let server = tokio::spawn(async move { server.accept(stream).await });
+        let res = utils::client(client, "localhost", "/", headers, &[]).await;
+        match allowed {
+            true => {
+                res.unwrap();
+                server.await.unwrap().unwrap();
+            }
+            false => {
+                assert_eq!(res.unwrap_err().to_string(), "expected upgrade connection");
+                let err = server.await.unwrap().unwrap_err();
+                assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+            }
+        }
+    }
+
+    let (req, _) = handshake::request("localhost", "/", [("Origin", "https://evil.com")]);
+    let (mut client, stream) = duplex(4096);
+    tokio::spawn(async move { server.accept(stream).await });
+    client.write_all(req.as_bytes()).await.unwrap();
+    let mut res = String::new();
+    client.read_to_string(&mut res).await.unwrap();
+    assert_eq!(res, "HTTP/1.1 403 Forbidden\r\n\r\n");
+}
+
+#[tokio::test]
+async fn mixed_case_headers() {
+    let res = "HTTP/1.1 101 Switching Protocols\r\nUPGRADE: websocket\r\nconnection: Upgrade\r\nSEC-WEBSOCKET-ACCEPT: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\r\n";
+    let http = utils::Http::parse(&mut res.as_bytes()).await.unwrap();
+    assert_eq!(http["upgrade"], "websocket");
+    assert_eq!(http["connection"], "Upgrade");
+    assert!(handshake::verify_accept_key(
+        "dGhlIHNhbXBsZSBub25jZQ==",
+        &http["sec-websocket-accept"]
+    ));
+
+    let client = respond(|key| {
+        handshake::response(key, None::<(&str, &str)>)
+            .replace("Sec-WebSocket-Accept", "sec-websocket-accept")
+    });
+    utils::client(client, "localhost", "/", &[], &[])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn fragmented_response() {
+    let client = respond_with(1, |key| {
+        handshake::response(key, [("Sec-WebSocket-Protocol", "chat")])
+    });
+    let (_, res) = utils::client(client, "localhost", "/", &[], &["chat"])
+        .await
+        .unwrap();
+    assert_eq!(res["sec-websocket-protocol"], "chat");
+}
+
+#[tokio::test]
+async fn frame_after_response() {
+    // the first frame arrives in the same write as the response.
+    let client = respond(|key| {
+        let mut res = handshake::response(key, None::<(&str, &str)>).into_bytes();
+        res.extend_from_slice(&[0x81, 5]);
+        res.extend_from_slice(b"Hello");
+        res
+    });
+    let (mut ws, _) = utils::client(client, "localhost", "/", &[], &[])
+        .await
+        .unwrap();
+    assert_eq!(
+        ws.recv_message().await.unwrap(),
+        Message::Text("Hello".into())
+    );
+}
+
+#[tokio::test]
+async fn truncated_head() {
+    for res in [
+        "",
+        "HTTP/1.1 101 Switching Protocols\r\n",
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n",
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: webs",
+    ] {
+        let err = utils::Http::parse(&mut res.as_bytes()).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof, "{res:?}");
+    }
+}
+
+#[tokio::test]
+async fn head_len_limit() {
+    let res = "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\r\n";
+    assert!(
+        utils::Http::parse_with_limit(&mut res.as_bytes(), res.len())
+            .await
+            .is_ok()
+    );
+    let err = utils::Http::parse_with_limit(&mut res.as_bytes(), res.len() - 1)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    // the limit ends exactly after a line break, before the empty line.
+    let err = utils::Http::parse_with_limit(&mut res.as_bytes(), res.len() - 2)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    let client = respond(|key| {
+        let cookie = "a".repeat(utils::MAX_HEAD_LEN);
+        handshake::response(key, [("Set-Cookie", cookie)])
+    });
+    let err = utils::client(client, "localhost", "/", &[], &[])
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "handshake headers too large");
+}
+
+#[tokio::test]
+async fn conversation() {
+    let (client, server) = duplex(64);
+    let server = tokio::spawn(async move {
+        let mut ws = utils::accept(server).await?;
+        assert_eq!(ws.recv_message().await?, Message::Text("Hello".into()));
+        ws.send_ping("?").await?;
+        ws.flush().await?;
+        assert_eq!(ws.recv_message().await?, Message::Pong(b"?".to_vec()));
+        ws.send(&[0; 1000][..]).await?;
+        ws.close(CloseCode::Normal).await
+    });
+
+    let (mut ws, _) = utils::client(client, "localhost", "/", &[], &[])
+        .await
+        .unwrap();
+    let fragments = [
+        (false, OpCode::Text, "He"),
+        (true, OpCode::Continuation, "llo"),
+    ];
+    for (fin, opcode, data) in fragments {
+        let data = data.as_bytes();
+        ws.send(Frame { fin, opcode, data }).await.unwrap();
+    }
+    assert_eq!(
+        ws.recv_message().await.unwrap(),
+        Message::Ping(b"?".to_vec())
+    );
+    assert_eq!(
+        ws.recv_message().await.unwrap(),
+        Message::Binary(vec![0; 1000])
+    );
+    let Event::Close { code, .. } = ws.recv().await.unwrap() else {
+        panic!("expected close event")
+    };
+    assert_eq!(code, Some(1000));
+    server.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn client_builder() {
+    let (client, server) = duplex(4096);
+    tokio::spawn(async move {
+        let mut server = BufReader::new(server);
+        let req = utils::Http::parse(&mut server).await.unwrap();
+        assert_eq!(req["origin"], "https://example.com");
+        assert_eq!(req["sec-websocket-protocol"], "chat, superchat");
+        let res = handshake::response(
+            &req["sec-websocket-key"],
+            [("Sec-WebSocket-Protocol", "superchat")],
+        );
+        server.write_all(res.as_bytes()).await.unwrap();
+    });
+    let (ws, res) = utils::ClientBuilder::new()
+        .origin("https://example.com")
+        .protocols(["chat", "superchat"])
+        .max_message_len(1024)
+        .keepalive(std::time::Duration::from_secs(30))
+        .client(client, "localhost", "/")
+        .await
+        .unwrap();
+    assert_eq!(res["sec-websocket-protocol"], "superchat");
+    assert_eq!(ws.max_message_len, 1024);
+    assert_eq!(ws.keepalive, Some(std::time::Duration::from_secs(30)));
+
+    let client = respond(|key| handshake::response(key, None::<(&str, &str)>));
+    let err = utils::ClientBuilder::new()
+        .max_head_len(64)
+        .client(client, "localhost", "/")
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "handshake headers too large");
+}
+
+#[tokio::test]
+async fn socket_options() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        for _ in 0..2 {
+            let (stream, _) = listener.accept().await.unwrap();
+            utils::accept(stream).await.unwrap();
+        }
+    });
+    let url = format!("ws://{addr}/");
+    let (ws, _) = utils::ClientBuilder::new()
+        .tcp_keepalive(Some(Duration::from_secs(60)), Some(Duration::from_secs(5)))
+        .connect(&url)
+        .await
+        .unwrap();
+    let stream = ws.stream.get_ref();
+    assert!(stream.nodelay().unwrap());
+    let socket = socket2::SockRef::from(stream);
+    assert!(socket.keepalive().unwrap());
+    #[cfg(target_os = "linux")]
+    {
+        assert_eq!(
+            socket.tcp_keepalive_time().unwrap(),
+            Duration::from_secs(60)
+        );
+        assert_eq!(
+            socket.tcp_keepalive_interval().unwrap(),
+            Duration::from_secs(5)
+        );
+    }
+    assert_eq!(stream.peer_addr().unwrap(), addr);
+    assert!(stream.local_addr().unwrap().ip().is_loopback());
+
+    let (ws, _) = utils::ClientBuilder::new()
+        .nodelay(false)
+        .connect(&url)
+        .await
+        .unwrap();
+    assert!(!ws.stream.get_ref().nodelay().unwrap());
+}
+
+#[tokio::test]
+async fn connected_stream() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (stream, peer) = listener.accept().await.unwrap();
+        utils::accept(stream).await.unwrap();
+        peer
+    });
+    let socket = tokio::net::TcpSocket::new_v4().unwrap();
+    socket.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let local = socket.local_addr().unwrap();
+    let stream = socket.connect(addr).await.unwrap();
+
+    let (ws, res) = utils::client(stream, &addr.to_string(), "/", &[], &[])
+        .await
+        .unwrap();
+    assert_eq!(res.prefix(), "HTTP/1.1 101 Switching Protocols");
+    assert_eq!(ws.stream.get_ref().local_addr().unwrap(), local);
+    assert_eq!(server.await.unwrap(), local);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn unix_socket() {
+    let socket = std::env::temp_dir().join(format!("web-socket-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&socket);
+    let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = utils::accept(stream).await.unwrap();
+        ws.send("Hello").await.unwrap();
+    });
+    let mut ws = utils::connect_unix(&socket, "/").await.unwrap();
+    assert_eq!(
+        ws.recv_message().await.unwrap(),
+        Message::Text("Hello".into())
+    );
+    server.await.unwrap();
+    std::fs::remove_file(&socket).unwrap();
+}
+
+#[tokio::test]
+async fn authorization() {
+    for (builder, expected) in [
+        (
+            utils::ClientBuilder::new().basic_auth("Aladdin", "open sesame"),
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==",
+        ),
+        (
+            utils::ClientBuilder::new().bearer_auth("mF_9.B5f-4.1JqM"),
+            "Bearer mF_9.B5f-4.1JqM",
+        ),
+    ] {
+        let (client, server) = duplex(4096);
+        tokio::spawn(async move {
+            let mut server = BufReader::new(server);
+            let req = utils::Http::parse(&mut server).await.unwrap();
+            assert_eq!(req["authorization"], expected);
+            let res = handshake::response(&req["sec-websocket-key"], None::<(&str, &str)>);
+            server.write_all(res.as_bytes()).await.unwrap();
+        });
+        builder.client(client, "localhost", "/").await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn proxy() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = BufReader::new(stream);
+        let req = utils::Http::parse(&mut stream).await.unwrap();
+        assert_eq!(req.prefix(), "CONNECT example.com:80 HTTP/1.1");
+        assert_eq!(req["proxy-authorization"], "Basic dXNlcjpwYXNz");
+        stream
+            .write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")
+            .await
+            .unwrap();
+        // acts as the websocket server at the other end of the tunnel.
+        let mut ws = utils::accept(stream.into_inner()).await.unwrap();
+        ws.send("tunneled").await.unwrap();
+    });
+    let (mut ws, _) = utils::ClientBuilder::new()
+        .proxy(addr, Some(("user", "pass")))
+        .connect("ws://example.com/chat")
+        .await
+        .unwrap();
+    assert_eq!(
+        ws.recv_message().await.unwrap(),
+        Message::Text("tunneled".into())
+    );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = BufReader::new(stream);
+        utils::Http::parse(&mut stream).await.unwrap();
+        stream
+            .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+            .await
+            .unwrap();
+    });
+    let err = utils::ClientBuilder::new()
+        .proxy(addr, None)
+        .connect("ws://example.com/chat")
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::ConnectionRefused);
+}
+
+#[tokio::test]
+async fn reconnect() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        for msg in ["one", "two"] {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = utils::accept(stream).await.unwrap();
+            assert_eq!(
+                ws.recv_message().await.unwrap(),
+                Message::Text("subscribe".into())
+            );
+            ws.send(msg).await.unwrap();
+            // the first connection is dropped, the second one is closed.
+            if msg == "two" {
+                ws.close(CloseCode::Normal).await.unwrap();
+            }
+        }
+    });
+    let messages = utils::ReconnectingClient::new(format!("ws://{addr}/"))
+        .subscribe("subscribe")
+        .backoff(Duration::from_millis(1), Duration::from_millis(10))
+        .into_stream();
+    let messages: Vec<_> = messages.take(2).map(Result::unwrap).collect().await;
+    assert_eq!(
+        messages,
+        [Message::Text("one".into()), Message::Text("two".into())]
+    );
+}
+
+#[tokio::test]
+async fn reconnect_errors() {
+    // permanent errors are returned right away.
+    let mut client = utils::ReconnectingClient::new("wss://localhost/");
+    let err = client.recv().await.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+    let messages = utils::ReconnectingClient::new(format!("ws://{addr}/"))
+        .backoff(Duration::from_millis(1), Duration::from_millis(10))
+        .max_attempts(3)
+        .into_stream();
+    let messages: Vec<_> = messages.collect().await;
+    assert_eq!(messages.len(), 1);
+    let err = messages.into_iter().next().unwrap().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::ConnectionRefused);
+}
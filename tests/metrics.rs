@@ -0,0 +1,48 @@
+#![cfg(feature = "metrics")]
+
+use std::io;
+use tokio::io::{duplex, AsyncWriteExt};
+use web_socket::*;
+
+#[tokio::test]
+async fn counters() -> io::Result<()> {
+    let (stream, mut peer) = duplex(64);
+    let (mut reader, mut writer) = WebSocket::client(stream).split();
+    peer.write_all(&[0x81, 2, b'H', b'i', 0x89, 1, b'!', 0x8A, 0])
+        .await?;
+    peer.write_all(&[0x83, 0]).await?;
+
+    writer.send("Hello").await?;
+    writer.send_all(["a", "b"]).await?;
+    for _ in 0..4 {
+        reader.recv_event().await?;
+    }
+
+    // shared by both halves.
+    let metrics = reader.metrics();
+    assert!(std::sync::Arc::ptr_eq(metrics, writer.metrics()));
+    assert_eq!((metrics.frames_sent(), metrics.bytes_sent()), (3, 7));
+    assert_eq!(
+        (metrics.frames_received(), metrics.bytes_received()),
+        (3, 3)
+    );
+    assert_eq!((metrics.pings_received(), metrics.pongs_received()), (1, 1));
+    assert_eq!(metrics.protocol_errors(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_write() -> io::Result<()> {
+    for role in [Role::Client, Role::Server] {
+        let (stream, peer) = duplex(64);
+        drop(peer);
+        let mut ws = WebSocket::from((stream, role));
+        assert!(ws.send("Hello").await.is_err());
+        assert!(ws.send_all(["a", "b"]).await.is_err());
+
+        // frames that never reached the endpoint aren't counted.
+        let metrics = ws.metrics();
+        assert_eq!((metrics.frames_sent(), metrics.bytes_sent()), (0, 0));
+    }
+    Ok(())
+}
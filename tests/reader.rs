@@ -0,0 +1,63 @@
+#![cfg(feature = "std")]
+
+use std::io;
+use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+use web_socket::*;
+
+#[tokio::test]
+async fn read_message() -> io::Result<()> {
+    let (stream, mut peer) = duplex(64);
+    let mut ws = WebSocket::client(stream);
+
+    // fragments with a ping in between, then a second message.
+    peer.write_all(&[0x02, 3, 1, 2, 3, 0x89, 0]).await?;
+    peer.write_all(&[0x80, 2, 4, 5, 0x82, 1, 6]).await?;
+
+    let mut buf = [0; 2];
+    let mut reader = ws.message_reader();
+    assert_eq!(reader.read(&mut buf).await?, 2);
+    assert_eq!(buf, [1, 2]);
+    let mut rest = vec![];
+    reader.read_to_end(&mut rest).await?;
+    assert_eq!(rest, [3, 4, 5]);
+    assert_eq!(reader.read(&mut buf).await?, 0);
+    drop(reader);
+
+    let mut out = vec![];
+    tokio::io::copy(&mut ws.message_reader(), &mut out).await?;
+    assert_eq!(out, [6]);
+
+    // auto-pong
+    let mut pong = [0; 6];
+    peer.read_exact(&mut pong).await?;
+    assert_eq!(pong[..2], [0x8A, 0x80]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn close_before_end() -> io::Result<()> {
+    let (stream, mut peer) = duplex(64);
+    let mut ws = WebSocket::client(stream);
+    peer.write_all(&[0x88, 0]).await?;
+    let mut buf = vec![];
+    assert_eq!(ws.message_reader().read_to_end(&mut buf).await?, 0);
+
+    let (stream, mut peer) = duplex(64);
+    let mut ws = WebSocket::client(stream);
+    peer.write_all(&[0x01, 1, b'a', 0x88, 0]).await?;
+    let err = ws.message_reader().read_to_end(&mut buf).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+    let (stream, mut peer) = duplex(64);
+    let mut ws = WebSocket::client(stream);
+    peer.write_all(&[0x81, 1, 0xFF]).await?;
+    let err = ws.message_reader().read_to_end(&mut buf).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    Ok(())
+}
+
+#[test]
+fn send() {
+    fn assert_send<T: Send>() {}
+    assert_send::<reader::MessageReader<'static, tokio::net::TcpStream>>();
+}
@@ -0,0 +1,800 @@
+#![cfg(feature = "std")]
+
+use std::{io, time::Duration};
+use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+use web_socket::*;
+
+async fn recv(role: Role, bytes: &[u8]) -> io::Result<Event> {
+    WebSocket::from((bytes, role)).recv_event().await
+}
+
+#[tokio::test]
+async fn close_event() -> io::Result<()> {
+    let Event::Close { code, reason } = recv(Role::Client, &[0x88, 0]).await? else {
+        panic!("expected close event")
+    };
+    assert_eq!((code, &*reason), (None, ""));
+
+    let Event::Close { code, reason } =
+        recv(Role::Client, &[0x88, 4, 0x03, 0xE8, b'o', b'k']).await?
+    else {
+        panic!("expected close event")
+    };
+    assert_eq!((code, &*reason), (Some(1000), "ok"));
+
+    let Event::Close { code, reason } = recv(Role::Client, &[0x88, 2, 0x03, 0xE8]).await? else {
+        panic!("expected close event")
+    };
+    assert_eq!((code, &*reason), (Some(1000), ""));
+
+    let event = recv(Role::Client, &[0x88, 1, 0x03]).await?;
+    assert!(matches!(
+        event,
+        Event::Error(ProtocolError::InvalidCloseCode)
+    ));
+
+    let event = recv(Role::Client, &[0x88, 2, 0x03, 0xEE]).await?;
+    assert!(matches!(
+        event,
+        Event::Error(ProtocolError::InvalidCloseCode)
+    ));
+
+    let event = recv(Role::Client, &[0x88, 3, 0x03, 0xE8, 0xFF]).await?;
+    assert!(matches!(event, Event::Error(ProtocolError::InvalidUtf8)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn auto_pong() -> io::Result<()> {
+    let (stream, mut peer) = duplex(64);
+    let mut ws = WebSocket::client(stream);
+
+    peer.write_all(&[0x89, 2, b'h', b'i']).await?;
+    assert!(matches!(ws.recv().await?, Event::Ping(data) if &*data == b"hi"));
+
+    let mut pong = [0; 8];
+    peer.read_exact(&mut pong).await?;
+    let frame = decode(&pong).unwrap().unwrap();
+    assert_eq!(
+        (frame.fin, frame.opcode, frame.len),
+        (true, OpCode::Pong, 2)
+    );
+
+    ws.auto_pong = false;
+    peer.write_all(&[0x89, 0, 0x8A, 0]).await?;
+    assert!(matches!(ws.recv().await?, Event::Ping(..)));
+    assert!(matches!(ws.recv().await?, Event::Pong(..)));
+
+    drop(ws);
+    assert_eq!(peer.read(&mut pong).await?, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn recv_message() -> io::Result<()> {
+    let (stream, mut peer) = duplex(64);
+    let mut ws = WebSocket::client(stream);
+    ws.auto_pong = false;
+
+    peer.write_all(&[0x01, 3, b'H', b'e', b'l']).await?;
+    peer.write_all(&[0x89, 1, b'!']).await?;
+    peer.write_all(&[0x80, 2, b'l', b'o']).await?;
+    peer.write_all(&[0x82, 1, 42]).await?;
+    peer.write_all(&[0x88, 2, 0x03, 0xE8]).await?;
+
+    assert_eq!(ws.recv_message().await?, Message::Ping(b"!".to_vec()));
+    assert_eq!(ws.recv_message().await?, Message::Text("Hello".into()));
+    assert_eq!(ws.recv_message().await?, Message::Binary(vec![42]));
+    assert_eq!(
+        ws.recv_message().await?,
+        Message::Close {
+            code: Some(1000),
+            reason: "".into()
+        }
+    );
+
+    // protocol errors can be told apart from i/o errors.
+    let (stream, mut peer) = duplex(64);
+    let mut ws = WebSocket::client(stream);
+    peer.write_all(&[0x83, 0]).await?;
+    let err = ws.recv_message().await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let err = err.get_ref().and_then(|err| err.downcast_ref());
+    assert_eq!(err, Some(&ProtocolError::UnknownOpCode));
+    Ok(())
+}
+
+#[tokio::test]
+async fn recv_message_into() -> io::Result<()> {
+    let (stream, mut peer) = duplex(64);
+    let mut ws = WebSocket::client(stream);
+    ws.auto_pong = false;
+
+    peer.write_all(&[0x01, 3, b'H', b'e', b'l']).await?;
+    peer.write_all(&[0x89, 1, b'!']).await?;
+    peer.write_all(&[0x00, 1, b'l']).await?;
+    peer.write_all(&[0x80, 1, b'o']).await?;
+    peer.write_all(&[0x82, 1, 42]).await?;
+    peer.write_all(&[0x88, 0]).await?;
+
+    let mut buf = Vec::new();
+    let ty = ws.recv_message_into(&mut buf).await?;
+    assert_eq!((ty, &buf[..]), (Some(MessageType::Text), &b"Hello"[..]));
+    buf.clear();
+    let ty = ws.recv_message_into(&mut buf).await?;
+    assert_eq!((ty, &buf[..]), (Some(MessageType::Binary), &[42][..]));
+    assert_eq!(ws.recv_message_into(&mut buf).await?, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn three_fragments() -> io::Result<()> {
+    // a tiny pipe splits every header and payload across reads.
+    let (stream, mut peer) = duplex(2);
+    let mut ws = WebSocket::client(stream);
+    tokio::spawn(async move {
+        peer.write_all(&[0x01, 4, b'o', b'n', b'e', b' ']).await?;
+        peer.write_all(&[0x00, 4, b't', b'w', b'o', b' ']).await?;
+        peer.write_all(&[0x80, 5, b't', b'h', b'r', b'e', b'e'])
+            .await?;
+        peer.write_all(&[0x81, 0]).await?;
+        io::Result::Ok(peer)
+    });
+    let mut buf = Vec::new();
+    ws.recv_message_into(&mut buf).await?;
+    assert_eq!(buf, b"one two three");
+    assert_eq!(ws.recv_message().await?, Message::Text("".into()));
+    Ok(())
+}
+
+#[tokio::test]
+async fn interleaved_control_frames() -> io::Result<()> {
+    let (stream, mut peer) = duplex(64);
+    let mut ws = WebSocket::client(stream);
+    peer.write_all(&[0x01, 3, b'H', b'e', b'l']).await?;
+    peer.write_all(&[0x89, 1, b'!']).await?;
+    peer.write_all(&[0x80, 2, b'l', b'o']).await?;
+    peer.write_all(&[0x88, 0]).await?;
+
+    let mut buf = Vec::new();
+    let ty = ws.recv_message_into(&mut buf).await?;
+    assert_eq!((ty, &buf[..]), (Some(MessageType::Text), &b"Hello"[..]));
+
+    let mut pong = [0; 7];
+    peer.read_exact(&mut pong).await?;
+    assert_eq!(pong[..2], [0x8A, 0x81]);
+    assert_eq!(pong[6] ^ pong[2], b'!');
+
+    assert_eq!(ws.recv_message_into(&mut buf).await?, None);
+    assert_eq!(ws.state(), State::Closed);
+    Ok(())
+}
+
+#[tokio::test]
+async fn utf8_payload() -> io::Result<()> {
+    let event = recv(Role::Client, &[0x81, 2, 0xC3, 0x28]).await?;
+    assert!(matches!(event, Event::Error(ProtocolError::InvalidUtf8)));
+
+    // "€" (E2 82 AC) split across fragments
+    let (stream, mut peer) = duplex(64);
+    let mut ws = WebSocket::client(stream);
+    peer.write_all(&[0x01, 2, b'a', 0xE2]).await?;
+    peer.write_all(&[0x00, 1, 0x82]).await?;
+    peer.write_all(&[0x80, 1, 0xAC]).await?;
+    assert_eq!(ws.recv_message().await?, Message::Text("a€".into()));
+
+    // incomplete code point at the end of the message
+    peer.write_all(&[0x01, 1, 0xE2]).await?;
+    peer.write_all(&[0x80, 1, 0x82]).await?;
+    assert!(matches!(ws.recv_event().await?, Event::Data { .. }));
+    assert!(matches!(
+        ws.recv_event().await?,
+        Event::Error(ProtocolError::InvalidUtf8)
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn masking() -> io::Result<()> {
+    let masked = Frame::from("Hello").encode_with([1, 2, 3, 4]);
+    let unmasked = Frame::from("Hello").encode_without_mask();
+
+    let event = recv(Role::Server, &unmasked).await?;
+    assert!(matches!(
+        event,
+        Event::Error(ProtocolError::ExpectedMaskedFrame)
+    ));
+
+    let event = recv(Role::Client, &masked).await?;
+    assert!(matches!(
+        event,
+        Event::Error(ProtocolError::ExpectedUnmaskedFrame)
+    ));
+
+    let event = recv(Role::Server, &masked).await?;
+    assert!(matches!(event, Event::Data { data, .. } if &*data == b"Hello"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn reserved_bits() -> io::Result<()> {
+    for rsv in [0x40, 0x20, 0x10] {
+        let event = recv(Role::Client, &[0x81 | rsv, 1, b'a']).await?;
+        assert!(matches!(event, Event::Error(ProtocolError::ReservedBits)));
+
+        let event = recv(Role::Client, &[0x89 | rsv, 0]).await?;
+        assert!(matches!(event, Event::Error(ProtocolError::ReservedBits)));
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn payload_len() -> io::Result<()> {
+    // 64-bit lengths that don't fit in memory, or in `usize` on 32-bit targets.
+    for len in [i64::MAX as u64, u32::MAX as u64 + 1] {
+        let mut bytes = vec![0x82, 127];
+        bytes.extend_from_slice(&len.to_be_bytes());
+        let event = recv(Role::Client, &bytes).await?;
+        assert!(matches!(
+            event,
+            Event::Error(ProtocolError::PayloadTooLarge)
+        ));
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn payload_len_64bit() -> io::Result<()> {
+    let data = vec![7; 65536 + 1];
+    let (stream, mut peer) = duplex(1024);
+    let mut ws = WebSocket::client(stream);
+    let frame = Frame::from(&data[..]).encode_without_mask();
+    assert_eq!(frame[1], 127);
+    tokio::spawn(async move { peer.write_all(&frame).await });
+    assert_eq!(ws.recv_message().await?, Message::Binary(data));
+
+    let event = recv(Role::Client, &[0x82, 127, 0x80, 0, 0, 0, 0, 0, 0, 1]).await?;
+    assert!(matches!(
+        event,
+        Event::Error(ProtocolError::InvalidPayloadLen)
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn control_frames() -> io::Result<()> {
+    // Close, Ping and Pong without `FIN`.
+    for opcode in [0x08, 0x09, 0x0A] {
+        let event = recv(Role::Client, &[opcode, 0]).await?;
+        assert!(matches!(
+            event,
+            Event::Error(ProtocolError::FragmentedControlFrame)
+        ));
+    }
+    let mut bytes = vec![0x89, 126, 0, 126];
+    bytes.extend_from_slice(&[0; 126]);
+    let event = recv(Role::Client, &bytes).await?;
+    assert!(matches!(
+        event,
+        Event::Error(ProtocolError::ControlFrameTooLarge)
+    ));
+
+    let mut bytes = vec![0x89, 125];
+    bytes.extend_from_slice(&[0; 125]);
+    assert!(matches!(recv(Role::Client, &bytes).await?, Event::Ping(_)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn fragment_order() -> io::Result<()> {
+    // continuation frame without a message in progress.
+    for bytes in [[0x00, 0], [0x80, 0]] {
+        let event = recv(Role::Client, &bytes).await?;
+        assert!(matches!(
+            event,
+            Event::Error(ProtocolError::InvalidDataFrame)
+        ));
+    }
+    // new data frame while a fragmented message is in progress.
+    for opcode in [0x01, 0x02, 0x81, 0x82] {
+        let bytes = [0x01, 0, opcode, 0];
+        let mut ws = WebSocket::from((&bytes[..], Role::Client));
+        assert!(matches!(ws.recv_event().await?, Event::Data { .. }));
+        let event = ws.recv_event().await?;
+        assert!(matches!(
+            event,
+            Event::Error(ProtocolError::InvalidDataFrame)
+        ));
+    }
+    // control frames are allowed between fragments, and a new message can start after the last one.
+    let bytes = [0x01, 0, 0x89, 0, 0x80, 0, 0x82, 0];
+    let mut ws = WebSocket::from((&bytes[..], Role::Client));
+    assert!(matches!(ws.recv_event().await?, Event::Data { .. }));
+    assert!(matches!(ws.recv_event().await?, Event::Ping(_)));
+    let Event::Data { ty, .. } = ws.recv_event().await? else {
+        panic!("expected data event")
+    };
+    assert!(matches!(
+        ty,
+        DataType::Stream(Stream::End(MessageType::Text))
+    ));
+    let Event::Data { ty, .. } = ws.recv_event().await? else {
+        panic!("expected data event")
+    };
+    assert!(matches!(ty, DataType::Complete(MessageType::Binary)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn reserved_opcodes() -> io::Result<()> {
+    for opcode in (3..=7).chain(11..=15) {
+        let event = recv(Role::Client, &[0x80 | opcode, 0]).await?;
+        assert!(
+            matches!(event, Event::Error(ProtocolError::UnknownOpCode)),
+            "{opcode}"
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn message_len() -> io::Result<()> {
+    let bytes = [0x02, 3, 1, 2, 3, 0x00, 3, 4, 5, 6, 0x80, 3, 7, 8, 9];
+    let mut ws = WebSocket::from((&bytes[..], Role::Client));
+    ws.max_message_len = 8;
+    assert!(matches!(ws.recv_event().await?, Event::Data { .. }));
+    assert!(matches!(ws.recv_event().await?, Event::Data { .. }));
+    assert!(matches!(
+        ws.recv_event().await?,
+        Event::Error(ProtocolError::MessageTooLarge)
+    ));
+
+    let mut ws = WebSocket::from((&bytes[..], Role::Client));
+    ws.max_message_len = 9;
+    let mut data = vec![];
+    while let Event::Data { data: chunk, .. } = ws.recv_event().await? {
+        data.extend_from_slice(&chunk);
+        if data.len() == 9 {
+            break;
+        }
+    }
+    assert_eq!(data, [1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+    // unfragmented message
+    let mut ws = WebSocket::from((&[0x82, 3, 1, 2, 3][..], Role::Client));
+    ws.max_message_len = 2;
+    assert!(matches!(
+        ws.recv_event().await?,
+        Event::Error(ProtocolError::MessageTooLarge)
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn keepalive() -> io::Result<()> {
+    let (client, server) = duplex(64);
+    let mut client = WebSocket::client(client);
+    let mut server = WebSocket::server(server);
+    client.keepalive = Some(Duration::from_millis(20));
+
+    // answered by the auto-pong of the server.
+    let (event, ping) = tokio::join!(client.recv(), server.recv());
+    assert!(matches!(event?, Event::Pong(data) if data.is_empty()));
+    assert!(matches!(ping?, Event::Ping(..)));
+
+    // unanswered.
+    let (event, ping) = tokio::join!(client.recv(), server.recv_event());
+    assert_eq!(event.unwrap_err().kind(), io::ErrorKind::TimedOut);
+    assert!(matches!(ping?, Event::Ping(..)));
+    // the connection has failed.
+    assert!(matches!(client.recv().await?, Event::Closed));
+    Ok(())
+}
+
+#[tokio::test]
+async fn recv_timeout() -> io::Result<()> {
+    let (stream, mut peer) = duplex(64);
+    let mut ws = WebSocket::client(stream);
+    let timeout = Duration::from_millis(20);
+
+    let err = ws.recv_timeout(timeout).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+    // a frame that started before the timeout is read to the end.
+    peer.write_all(&[0x81, 2, b'h']).await?;
+    let (event, _) = tokio::join!(ws.recv_timeout(timeout), async {
+        tokio::time::sleep(timeout * 2).await;
+        peer.write_all(b"i").await
+    });
+    assert!(matches!(event?, Event::Data { data, .. } if &*data == b"hi"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn close_handshake() -> io::Result<()> {
+    let (client, server) = duplex(64);
+    let mut client = WebSocket::client(client);
+    let mut server = WebSocket::server(server);
+    assert_eq!(client.state(), State::Open);
+
+    server.send("pending").await?;
+    let (closed, event) = tokio::join!(client.close(CloseCode::Away), server.recv());
+    closed?;
+    assert!(matches!(
+        event?,
+        Event::Close {
+            code: Some(1001),
+            ..
+        }
+    ));
+    assert_eq!(
+        (client.state(), server.state()),
+        (State::Closed, State::Closed)
+    );
+
+    // the server has echoed the status code.
+    let (client, server) = duplex(64);
+    let mut client = WebSocket::client(client);
+    let mut server = WebSocket::server(server);
+    server.send_close(CloseCode::Normal).await?;
+    assert_eq!(server.state(), State::Closing);
+    assert!(matches!(
+        client.recv().await?,
+        Event::Close {
+            code: Some(1000),
+            ..
+        }
+    ));
+    assert_eq!(client.state(), State::Closed);
+    assert!(matches!(
+        server.recv_event().await?,
+        Event::Close {
+            code: Some(1000),
+            ..
+        }
+    ));
+    assert_eq!(server.state(), State::Closed);
+    Ok(())
+}
+
+#[tokio::test]
+async fn closed() -> io::Result<()> {
+    let (client, server) = duplex(64);
+    let mut client = WebSocket::client(client);
+    let mut server = WebSocket::server(server);
+
+    let (closed, event) = tokio::join!(client.close(()), server.recv());
+    closed?;
+    assert!(matches!(event?, Event::Close { code: None, .. }));
+
+    for ws in [&mut client, &mut server] {
+        let err = ws.send("Hello").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+        let err = ws.send_ping([]).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+        // the end of the connection is not an error.
+        assert!(matches!(ws.recv().await?, Event::Closed));
+        assert!(matches!(ws.recv_ref().await?, EventRef::Closed));
+        let event = ws.recv_timeout(Duration::from_secs(1)).await?;
+        assert!(matches!(event, Event::Closed));
+        assert_eq!(ws.recv_message().await?, Message::Closed);
+        assert_eq!(ws.recv_message_into(&mut vec![]).await?, None);
+    }
+    // already closed
+    client.close(()).await
+}
+
+#[tokio::test]
+async fn split() -> io::Result<()> {
+    let (client, server) = duplex(64);
+    let (mut reader, mut writer) = WebSocket::client(client).split();
+    let mut server = WebSocket::server(server);
+
+    let echo = tokio::spawn(async move {
+        while let Event::Data { data, .. } = server.recv().await? {
+            server.send(&*data).await?;
+        }
+        io::Result::Ok(())
+    });
+    let recv = tokio::spawn(async move {
+        let mut received = vec![];
+        while let Event::Data { data, .. } = reader.recv_event().await? {
+            received.push(data);
+        }
+        io::Result::Ok(received)
+    });
+    for i in 0..10_u8 {
+        writer.send(&[i][..]).await?;
+    }
+    writer.send_close(()).await?;
+    echo.await??;
+    let received = recv.await??;
+    assert_eq!(received.len(), 10);
+    assert!(received
+        .iter()
+        .enumerate()
+        .all(|(i, data)| **data == [i as u8]));
+    Ok(())
+}
+
+#[tokio::test]
+async fn split_close() -> io::Result<()> {
+    let (client, server) = duplex(64);
+    let (mut reader, mut writer) = WebSocket::client(client).split();
+    let mut server = WebSocket::server(server);
+
+    server.send_close(CloseCode::Normal).await?;
+    assert!(matches!(reader.recv_event().await?, Event::Close { .. }));
+    assert_eq!(writer.state(), State::Closing);
+
+    // the write half knows that the endpoint has closed the connection.
+    let err = writer.send("Hello").await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+    writer.send_close(CloseCode::Normal).await?;
+    assert_eq!(
+        (reader.state(), writer.state()),
+        (State::Closed, State::Closed)
+    );
+    Ok(())
+}
+
+#[test]
+fn close_codes() {
+    use ProtocolError::*;
+    for (err, code) in [
+        (ReservedBits, 1002),
+        (ExpectedMaskedFrame, 1002),
+        (ExpectedUnmaskedFrame, 1002),
+        (UnknownOpCode, 1002),
+        (FragmentedControlFrame, 1002),
+        (ControlFrameTooLarge, 1002),
+        (InvalidDataFrame, 1002),
+        (InvalidPayloadLen, 1002),
+        (InvalidCloseCode, 1002),
+        (InvalidCompressedPayload, 1002),
+        (InvalidUtf8, 1007),
+        (PayloadTooLarge, 1009),
+        (MessageTooLarge, 1009),
+    ] {
+        assert_eq!(u16::from(err.close_code()), code, "{err}");
+    }
+}
+
+#[tokio::test]
+async fn close_on_error() -> io::Result<()> {
+    let (stream, mut peer) = duplex(64);
+    let mut ws = WebSocket::client(stream);
+    peer.write_all(&[0x81, 2, 0xC3, 0x28]).await?;
+    let event = ws.recv().await?;
+    assert!(matches!(event, Event::Error(ProtocolError::InvalidUtf8)));
+    assert_eq!(ws.state(), State::Closed);
+
+    let mut close = [0; 8];
+    peer.read_exact(&mut close).await?;
+    assert_eq!(close[..2], [0x88, 0x82]);
+    let mask = [close[2], close[3], close[4], close[5]];
+    assert_eq!(
+        [close[6] ^ mask[0], close[7] ^ mask[1]],
+        1007_u16.to_be_bytes()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn borrowed_event() -> io::Result<()> {
+    let mut bytes = Frame::from("Hello").encode_with([1, 2, 3, 4]);
+    let ping = Frame {
+        fin: true,
+        opcode: OpCode::Ping,
+        data: b"ping",
+    };
+    bytes.extend(ping.encode_with([5, 6, 7, 8]));
+    bytes.extend(Frame::from("Hi").encode_with([1, 2, 3, 4]));
+    bytes.extend([0x88, 0x84, 0, 0, 0, 0, 0x03, 0xE8, b'o', b'k']);
+    let mut ws = WebSocket::from((&bytes[..], Role::Server));
+
+    let event = ws.recv_event_ref().await?;
+    assert!(matches!(event, EventRef::Data { data: b"Hello", .. }));
+    let event = ws.recv_event_ref().await?;
+    assert!(matches!(event, EventRef::Ping(b"ping")));
+    let event = Event::from(ws.recv_event_ref().await?);
+    assert!(matches!(event, Event::Data { data, .. } if &*data == b"Hi"));
+    let event = ws.recv_event_ref().await?;
+    assert!(matches!(
+        event,
+        EventRef::Close {
+            code: Some(1000),
+            reason: "ok"
+        }
+    ));
+    assert_eq!(ws.state(), State::Closing);
+    Ok(())
+}
+
+#[tokio::test]
+async fn unmask_payload() -> io::Result<()> {
+    // lengths around the word size cover the unaligned tail.
+    for len in [1, 7, 8, 9, 15, 300, 65536] {
+        let payload: Vec<u8> = (0..len).map(|n| n as u8).collect();
+        let first = Frame {
+            fin: false,
+            opcode: OpCode::Binary,
+            data: &payload[..len / 2],
+        };
+        let last = Frame {
+            fin: true,
+            opcode: OpCode::Continuation,
+            data: &payload[len / 2..],
+        };
+        let mut bytes = first.encode_with([0x12, 0x34, 0x56, 0x78]);
+        bytes.extend(last.encode_with([0xAB, 0xCD, 0xEF, 0x01]));
+
+        let (stream, mut peer) = duplex(1024);
+        let mut ws = WebSocket::server(stream);
+        tokio::spawn(async move { peer.write_all(&bytes).await });
+        assert_eq!(ws.recv_message().await?, Message::Binary(payload));
+    }
+    Ok(())
+}
+
+/// Yields one byte per read, and `Pending` in between.
+struct OneByte<'a> {
+    bytes: &'a [u8],
+    pending: bool,
+}
+
+impl tokio::io::AsyncRead for OneByte<'_> {
+    fn poll_read(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> std::task::Poll<io::Result<()>> {
+        self.pending = !self.pending;
+        if self.pending {
+            cx.waker().wake_by_ref();
+            return std::task::Poll::Pending;
+        }
+        if let Some((byte, rest)) = self.bytes.split_first() {
+            buf.put_slice(&[*byte]);
+            self.bytes = rest;
+        }
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn header_across_reads() -> io::Result<()> {
+    // 2 + 8 bytes of length + 4 bytes of mask.
+    let data = vec![7; 65536];
+    let mut bytes = Frame::from(&data[..]).encode_with([1, 2, 3, 4]);
+    bytes.extend(Frame::from("Hi").encode_with([5, 6, 7, 8]));
+    let stream = OneByte {
+        bytes: &bytes,
+        pending: false,
+    };
+    let mut ws = WebSocket::from((stream, Role::Server));
+    let event = ws.recv_event().await?;
+    assert!(matches!(event, Event::Data { data: ref d, .. } if **d == data[..]));
+    let event = ws.recv_event().await?;
+    assert!(matches!(event, Event::Data { data, .. } if &*data == b"Hi"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn empty_frames() -> io::Result<()> {
+    let (client, server) = duplex(64);
+    let mut client = WebSocket::client(client);
+    let mut server = WebSocket::server(server);
+
+    client.send("").await?;
+    client.send(&b""[..]).await?;
+    client.send_ping("").await?;
+    client
+        .send(Frame {
+            fin: false,
+            opcode: OpCode::Binary,
+            data: b"",
+        })
+        .await?;
+    client
+        .send(Frame {
+            fin: true,
+            opcode: OpCode::Continuation,
+            data: b"",
+        })
+        .await?;
+    client.send_close(()).await?;
+
+    assert_eq!(server.recv_message().await?, Message::Text("".into()));
+    assert_eq!(server.recv_message().await?, Message::Binary(vec![]));
+    assert_eq!(server.recv_message().await?, Message::Ping(vec![]));
+    assert_eq!(server.recv_message().await?, Message::Binary(vec![]));
+    assert_eq!(
+        server.recv_message().await?,
+        Message::Close {
+            code: None,
+            reason: "".into()
+        }
+    );
+
+    // auto-pong and close echo
+    assert_eq!(client.recv_message().await?, Message::Pong(vec![]));
+    assert!(matches!(
+        client.recv_message().await?,
+        Message::Close { code: None, .. }
+    ));
+    assert_eq!(client.state(), State::Closed);
+    Ok(())
+}
+
+#[test]
+fn data_type() {
+    let ty = DataType::Stream(Stream::Next(MessageType::Text));
+    assert_eq!(ty.ty(), MessageType::Text);
+    assert!(ty.is_text() && !ty.is_binary());
+    assert!(DataType::Complete(MessageType::Binary).is_binary());
+    assert_ne!(ty, DataType::Stream(Stream::End(MessageType::Text)));
+}
+
+#[tokio::test]
+async fn recv_ref() -> io::Result<()> {
+    let (stream, mut peer) = duplex(64);
+    let mut ws = WebSocket::client(stream);
+    peer.write_all(&[0x81, 2, b'H', b'i', 0x89, 1, b'!'])
+        .await?;
+    peer.write_all(&[0x88, 2, 0x03, 0xE8]).await?;
+
+    let event = ws.recv_ref().await?;
+    assert!(matches!(event, EventRef::Data { data: b"Hi", .. }));
+    let event = ws.recv_ref().await?;
+    assert!(matches!(event, EventRef::Ping(b"!")));
+    let event = ws.recv_ref().await?;
+    assert!(matches!(
+        event,
+        EventRef::Close {
+            code: Some(1000),
+            ..
+        }
+    ));
+    assert_eq!(ws.state(), State::Closed);
+
+    // auto-pong, then the echoed Close frame.
+    let mut buf = [0; 7 + 8];
+    peer.read_exact(&mut buf).await?;
+    assert_eq!(buf[..2], [0x8A, 0x81]);
+    assert_eq!(buf[6] ^ buf[2], b'!');
+    assert_eq!(buf[7..9], [0x88, 0x82]);
+    assert!(matches!(ws.recv_ref().await?, EventRef::Closed));
+    Ok(())
+}
+
+#[tokio::test]
+async fn eof() -> io::Result<()> {
+    for (bytes, kind) in [
+        (&[][..], io::ErrorKind::UnexpectedEof),
+        // in the header
+        (&[0x81], io::ErrorKind::ConnectionAborted),
+        (&[0x81, 126, 0], io::ErrorKind::ConnectionAborted),
+        // in the payload
+        (&[0x81, 5, b'H', b'e'], io::ErrorKind::ConnectionAborted),
+        // between the fragments of a message
+        (&[0x01, 2, b'H', b'e'], io::ErrorKind::ConnectionAborted),
+    ] {
+        let mut ws = WebSocket::from((bytes, Role::Client));
+        let err = loop {
+            match ws.recv_event().await {
+                Ok(Event::Data { .. }) => {}
+                Ok(event) => panic!("unexpected event: {event:?}"),
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(err.kind(), kind, "{bytes:?}");
+    }
+
+    // a complete message, then the end of the stream.
+    let mut ws = WebSocket::from((&[0x81, 2, b'H', b'i'][..], Role::Client));
+    assert!(matches!(ws.recv_event().await?, Event::Data { .. }));
+    let err = ws.recv_event().await.unwrap_err();
+    assert_eq!(err.to_string(), "connection closed");
+    Ok(())
+}
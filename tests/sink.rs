@@ -0,0 +1,58 @@
+#![cfg(feature = "sink")]
+
+use futures_util::{stream, SinkExt, StreamExt};
+use std::io;
+use tokio::io::duplex;
+use web_socket::*;
+
+#[tokio::test]
+async fn messages() -> io::Result<()> {
+    let (client, server) = duplex(1024);
+    let mut client = WebSocket::client(client);
+    let mut server = WebSocket::server(server);
+
+    SinkExt::send(&mut client, Message::Text("Hello".into())).await?;
+    assert_eq!(server.recv_message().await?, Message::Text("Hello".into()));
+
+    // buffered until flushed.
+    let messages = (0..3_u8).map(|i| Ok(Message::Binary(vec![i])));
+    stream::iter(messages).forward(&mut client).await?;
+    for i in 0..3 {
+        assert_eq!(server.recv_message().await?, Message::Binary(vec![i]));
+    }
+
+    SinkExt::close(&mut client).await?;
+    assert_eq!(client.state(), State::Closing);
+    assert_eq!(
+        server.recv_message().await?,
+        Message::Close {
+            code: None,
+            reason: "".into()
+        }
+    );
+    let err = SinkExt::send(&mut client, Message::Ping(vec![]))
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_close() {
+    let mut ws = WebSocket::server(Vec::new());
+    for (code, reason) in [
+        (None, "reason"),
+        (Some(1005), ""),
+        (Some(1000), &*"x".repeat(124)),
+    ] {
+        let msg = Message::Close {
+            code,
+            reason: reason.into(),
+        };
+        let err = SinkExt::send(&mut ws, msg).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+    let err = SinkExt::send(&mut ws, Message::Closed).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(ws.stream.is_empty());
+}
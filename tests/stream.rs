@@ -0,0 +1,66 @@
+#![cfg(feature = "stream")]
+
+use futures_util::StreamExt;
+use std::io;
+use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+use web_socket::*;
+
+#[tokio::test]
+async fn messages() -> io::Result<()> {
+    let (stream, mut peer) = duplex(64);
+    let mut messages = WebSocket::client(stream).into_stream();
+
+    // fragmented text, ping and close.
+    peer.write_all(&[0x01, 2, b'H', b'e', 0x80, 3, b'l', b'l', b'o'])
+        .await?;
+    peer.write_all(&[0x89, 0, 0x88, 2, 0x03, 0xE8]).await?;
+
+    assert_eq!(
+        messages.next().await.unwrap()?,
+        Message::Text("Hello".into())
+    );
+    assert_eq!(messages.next().await.unwrap()?, Message::Ping(vec![]));
+    assert_eq!(
+        messages.next().await.unwrap()?,
+        Message::Close {
+            code: Some(1000),
+            reason: "".into()
+        }
+    );
+    assert!(messages.next().await.is_none());
+
+    // the pong and echoed close frame.
+    let mut buf = [0; 14];
+    peer.read_exact(&mut buf).await?;
+    assert_eq!((buf[0], buf[6]), (0x8A, 0x88));
+
+    let ws = messages.into_inner().unwrap();
+    assert_eq!(ws.state(), State::Closed);
+    Ok(())
+}
+
+#[tokio::test]
+async fn end_of_stream() -> io::Result<()> {
+    let (stream, mut peer) = duplex(64);
+    let mut messages = WebSocket::client(stream).into_stream();
+    peer.write_all(&[0x81, 2, b'H', b'i']).await?;
+    drop(peer);
+    assert_eq!(messages.next().await.unwrap()?, Message::Text("Hi".into()));
+    assert!(messages.next().await.is_none());
+
+    // the connection ends in the middle of a frame.
+    let (stream, mut peer) = duplex(64);
+    let mut messages = WebSocket::client(stream).into_stream();
+    peer.write_all(&[0x81, 2, b'H']).await?;
+    drop(peer);
+    let err = messages.next().await.unwrap().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionAborted);
+    assert!(messages.next().await.is_none());
+    Ok(())
+}
+
+#[test]
+fn send() {
+    fn assert_send<T: Send>() {}
+    assert_send::<stream::MessageStream<tokio::net::TcpStream>>();
+}
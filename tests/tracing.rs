@@ -0,0 +1,74 @@
+#![cfg(feature = "tracing")]
+
+use std::{
+    fmt, io,
+    sync::{Arc, Mutex},
+};
+use tokio::io::{duplex, AsyncWriteExt};
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Metadata, Subscriber,
+};
+use web_socket::{CloseCode, WebSocket};
+
+/// Collects the messages of the events.
+#[derive(Clone, Default)]
+struct Messages(Arc<Mutex<Vec<String>>>);
+
+impl Visit for Messages {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0.lock().unwrap().push(format!("{value:?}"));
+        }
+    }
+}
+
+impl Subscriber for Messages {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(1)
+    }
+
+    fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        event.record(&mut self.clone());
+    }
+
+    fn enter(&self, _: &span::Id) {}
+
+    fn exit(&self, _: &span::Id) {}
+}
+
+#[tokio::test]
+async fn events() -> io::Result<()> {
+    let messages = Messages::default();
+    let _guard = tracing::subscriber::set_default(messages.clone());
+
+    let (stream, mut peer) = duplex(64);
+    let mut ws = WebSocket::client(stream);
+    peer.write_all(&[0x81, 2, b'H', b'i', 0x88, 2, 0x03, 0xE8])
+        .await?;
+    ws.recv().await?;
+    ws.send("Hello").await?;
+    ws.recv().await?;
+    ws.close(CloseCode::Normal).await?;
+
+    assert_eq!(
+        *messages.0.lock().unwrap(),
+        [
+            "frame received",
+            "sending frame",
+            "close frame received",
+            "sending frame",
+            "close frame sent",
+            "closing handshake completed",
+        ]
+    );
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use web_socket::validate::{Utf8Status, Utf8Validator};
+
+fn validate_chunks(data: &[u8], chunk_size: usize) -> Utf8Status {
+    let mut utf8 = Utf8Validator::default();
+    let mut status = Utf8Status::Valid;
+    for chunk in data.chunks(chunk_size) {
+        status = utf8.feed(chunk);
+        if status == Utf8Status::Invalid {
+            break;
+        }
+    }
+    status
+}
+
+// Autobahn test suite, case 6.2.x: `Hello-µ@ßöäüàá-UTF-8!!`
+#[test]
+fn valid_split() {
+    let text = "Hello-µ@ßöäüàá-UTF-8!!".as_bytes();
+    for chunk_size in 1..=text.len() {
+        assert_eq!(validate_chunks(text, chunk_size), Utf8Status::Valid);
+    }
+    let text = "κόσμε𝄞€".as_bytes();
+    for chunk_size in 1..=text.len() {
+        assert_eq!(validate_chunks(text, chunk_size), Utf8Status::Valid);
+    }
+}
+
+// Autobahn test suite, case 6.3.x and 6.4.x
+#[test]
+fn invalid_split() {
+    let vectors: [&[u8]; 4] = [
+        // 6.3.1: `κόσμε` followed by an encoded surrogate, followed by `edited`
+        b"\xce\xba\xe1\xbd\xb9\xcf\x83\xce\xbc\xce\xb5\xed\xa0\x80\x65\x64\x69\x74\x65\x64",
+        // 6.4.1: code point above U+10FFFF
+        b"\xce\xba\xe1\xbd\xb9\xcf\x83\xce\xbc\xce\xb5\xf4\x90\x80\x80\x65\x64\x69\x74\x65\x64",
+        // 6.4.3: overlong encoding
+        b"\xce\xba\xe1\xbd\xb9\xcf\x83\xce\xbc\xce\xb5\xc0\xaf",
+        // invalid continuation byte
+        b"\xe2\x82\x28",
+    ];
+    for data in vectors {
+        for chunk_size in 1..=data.len() {
+            assert_eq!(validate_chunks(data, chunk_size), Utf8Status::Invalid);
+        }
+    }
+}
+
+#[test]
+fn incomplete() {
+    let mut utf8 = Utf8Validator::default();
+    assert_eq!(utf8.feed(b"\xf0\x9d"), Utf8Status::Incomplete);
+    assert_eq!(utf8.feed(b""), Utf8Status::Incomplete);
+    assert_eq!(utf8.feed(b"\x84"), Utf8Status::Incomplete);
+    assert_eq!(utf8.feed(b"\x9e"), Utf8Status::Valid);
+
+    assert_eq!(utf8.feed(b"\xe2\x82"), Utf8Status::Incomplete);
+    utf8.reset();
+    assert_eq!(utf8.feed(b"ok"), Utf8Status::Valid);
+}